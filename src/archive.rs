@@ -0,0 +1,225 @@
+use crate::Handler;
+use ring::digest;
+use serenity::model::channel::Attachment;
+use serenity::model::id::AttachmentId;
+use serenity::model::id::ChannelId;
+use serenity::model::id::GuildId;
+use serenity::model::id::MessageId;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+//attachments are stored next to the bot, named after the sha-256 of their
+//contents so the same file posted twice is only stored once
+const ARCHIVE_DIR: &str = "./attachments";
+//archived attachments are deleted after this long
+const ARCHIVE_RETENTION_DAYS: i64 = 30;
+//how often expired attachments are looked for
+const PRUNE_INTERVAL_SECS: i64 = 86400;
+const SECONDS_IN_DAY: i64 = 86400;
+
+//numbers the temporary files of writes to the archive
+static PARTIAL_WRITES: AtomicU64 = AtomicU64::new(0);
+
+//an attachment that was saved to the archive
+#[derive(Clone)]
+pub struct ArchivedAttachment {
+    pub attachment_id: AttachmentId,
+    pub message_id: MessageId,
+    pub channel_id: ChannelId,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl ArchivedAttachment {
+    pub async fn read(&self) -> io::Result<Vec<u8>> {
+        tokio::fs::read(archive_path(&self.sha256)).await
+    }
+}
+
+impl Handler {
+    //download the attachments of a message while discord's links still work
+    pub async fn archive_attachments(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        message_id: MessageId,
+        channel_id: ChannelId,
+        attachments: &[Attachment],
+    ) {
+        if attachments.is_empty() {
+            return;
+        }
+        self.prune_archive().await;
+
+        //files too big to upload to the log channel could never be shown
+        let limit = self.upload_limit(ctx, g_id).await;
+        let archived = self.archived_attachments(message_id);
+        for attachment in attachments {
+            //edits send the attachments that were already archived again
+            if attachment.size > limit
+                || archived
+                    .iter()
+                    .any(|archived| archived.attachment_id == attachment.id)
+            {
+                continue;
+            }
+
+            let bytes = match attachment.download().await {
+                Ok(bytes) => bytes,
+                Err(why) => {
+                    eprintln!(
+                        "archive_attachments(): unable to download {}: {why}",
+                        attachment.url
+                    );
+                    continue;
+                }
+            };
+            //hashing and writing files of up to 100 MiB would hold up other
+            //events on this worker
+            let size = bytes.len() as u64;
+            let stored = tokio::task::spawn_blocking(move || store(&bytes))
+                .await
+                .unwrap_or_else(|why| Err(io::Error::other(why)));
+            let sha256 = match stored {
+                Ok(sha256) => sha256,
+                Err(why) => {
+                    eprintln!(
+                        "archive_attachments(): unable to store {}: {why}",
+                        attachment.filename
+                    );
+                    continue;
+                }
+            };
+
+            let record = ArchivedAttachment {
+                attachment_id: attachment.id,
+                message_id,
+                channel_id,
+                filename: attachment.filename.clone(),
+                content_type: attachment.content_type.clone(),
+                size,
+                sha256,
+            };
+            if let Err(why) = self.database.lock().unwrap().insert_attachment(&record) {
+                eprintln!(
+                    "archive_attachments(): unable to index {}: {why}",
+                    record.filename
+                );
+            }
+        }
+    }
+
+    //delete attachments archived longer ago than the retention period,
+    //at most once every PRUNE_INTERVAL_SECS
+    async fn prune_archive(&self) {
+        let now = Timestamp::now().unix_timestamp();
+        {
+            let mut pruned_at = self.archive_pruned.lock().unwrap();
+            if pruned_at.is_some_and(|pruned_at| now - pruned_at < PRUNE_INTERVAL_SECS) {
+                return;
+            }
+            *pruned_at = Some(now);
+        }
+
+        let expired_before = now - ARCHIVE_RETENTION_DAYS * SECONDS_IN_DAY;
+        let unused = match self
+            .database
+            .lock()
+            .unwrap()
+            .prune_attachments(expired_before)
+        {
+            Ok(unused) => unused,
+            Err(why) => {
+                eprintln!("prune_archive(): unable to prune the index: {why}");
+                return;
+            }
+        };
+        //files still used by newer messages are kept
+        for sha256 in unused {
+            if let Err(why) = tokio::fs::remove_file(archive_path(&sha256)).await {
+                eprintln!("prune_archive(): unable to delete {sha256}: {why}");
+            }
+        }
+    }
+
+    //the archived attachments of a message
+    pub fn archived_attachments(&self, message_id: MessageId) -> Vec<ArchivedAttachment> {
+        match self
+            .database
+            .lock()
+            .unwrap()
+            .message_attachments(message_id.0)
+        {
+            Ok(archived) => archived,
+            Err(why) => {
+                eprintln!(
+                    "archived_attachments(): unable to read the index of {message_id}: {why}"
+                );
+                vec![]
+            }
+        }
+    }
+}
+
+//find the archived copy of an attachment
+pub fn find_archived<'a>(
+    attachment: &Attachment,
+    archived: &'a [ArchivedAttachment],
+) -> Option<&'a ArchivedAttachment> {
+    archived
+        .iter()
+        .find(|archived| archived.attachment_id == attachment.id)
+}
+
+//name a file so it can be referenced by an `attachment://` url, which only
+//allows some characters
+pub fn upload_name(filename: &str) -> String {
+    filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+//write the contents to the archive unless they are already there, returning
+//their hash
+fn store(bytes: &[u8]) -> io::Result<String> {
+    let sha256: String = digest::digest(&digest::SHA256, bytes)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let path = archive_path(&sha256);
+    if path.exists() {
+        return Ok(sha256);
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    //write to a temporary file first so a crash never leaves half a file
+    //under the final name, every write gets its own so that the same file
+    //being stored twice at once doesn't rename the other's file away
+    let write = PARTIAL_WRITES.fetch_add(1, Ordering::Relaxed);
+    let partial = path.with_extension(format!("{write}.partial"));
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, &path)?;
+    Ok(sha256)
+}
+
+//files are spread over directories named after the start of their hash
+fn archive_path(sha256: &str) -> PathBuf {
+    PathBuf::from(ARCHIVE_DIR).join(&sha256[..2]).join(sha256)
+}
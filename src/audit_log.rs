@@ -0,0 +1,123 @@
+use serenity::model::guild::audit_log::Action;
+use serenity::model::guild::audit_log::AuditLogEntry;
+use serenity::model::id::ChannelId;
+use serenity::model::id::GuildId;
+use serenity::model::id::MessageId;
+use serenity::model::id::UserId;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use std::time::Duration;
+
+//entries older than this are not about the event being logged
+const AUDIT_WINDOW_SECS: i64 = 15;
+//discord can send an event before its audit log entry exists
+const AUDIT_RETRY_DELAY: Duration = Duration::from_secs(2);
+const AUDIT_FETCH_LIMIT: u8 = 10;
+
+//who did an action and why, as recorded in the audit log
+pub struct AuditEntry {
+    pub action: Action,
+    pub moderator: UserId,
+    pub reason: Option<String>,
+    //the message of message actions like pins
+    pub message_id: Option<MessageId>,
+}
+
+impl AuditEntry {
+    pub fn moderator_mention(&self) -> String {
+        format!("<@{}>", self.moderator)
+    }
+
+    pub fn reason_or_default(&self) -> String {
+        match &self.reason {
+            Some(reason) => reason.clone(),
+            None => "no reason given".to_owned(),
+        }
+    }
+}
+
+//find the audit log entry of one of these actions done to target_id just now
+pub async fn find_entry(
+    ctx: &Context,
+    g_id: GuildId,
+    actions: &[Action],
+    target_id: u64,
+) -> Option<AuditEntry> {
+    find_matching(ctx, g_id, actions, |entry| {
+        entry.target_id == Some(target_id)
+    })
+    .await
+}
+
+//find the audit log entry of one of these actions done in a channel just now,
+//for actions whose target is not the channel itself
+pub async fn find_in_channel(
+    ctx: &Context,
+    g_id: GuildId,
+    actions: &[Action],
+    c_id: ChannelId,
+) -> Option<AuditEntry> {
+    find_matching(ctx, g_id, actions, |entry| {
+        entry
+            .options
+            .as_ref()
+            .is_some_and(|options| options.channel_id == Some(c_id))
+    })
+    .await
+}
+
+async fn find_matching(
+    ctx: &Context,
+    g_id: GuildId,
+    actions: &[Action],
+    matches: impl Fn(&AuditLogEntry) -> bool,
+) -> Option<AuditEntry> {
+    if let Some(entry) = fetch_entry(ctx, g_id, actions, &matches).await {
+        return Some(entry);
+    }
+    tokio::time::sleep(AUDIT_RETRY_DELAY).await;
+    fetch_entry(ctx, g_id, actions, &matches).await
+}
+
+async fn fetch_entry(
+    ctx: &Context,
+    g_id: GuildId,
+    actions: &[Action],
+    matches: &impl Fn(&AuditLogEntry) -> bool,
+) -> Option<AuditEntry> {
+    //let discord filter by action when only one is wanted
+    let action_type = match actions {
+        [action] => Some(action.num()),
+        _ => None,
+    };
+
+    //the audit log can only be read with the view audit log permission
+    let audit_logs = match g_id
+        .audit_logs(ctx, action_type, None, None, Some(AUDIT_FETCH_LIMIT))
+        .await
+    {
+        Ok(audit_logs) => audit_logs,
+        Err(why) => {
+            eprintln!("fetch_entry(): unable to read the audit log of {g_id}: {why}");
+            return None;
+        }
+    };
+
+    let now = Timestamp::now().unix_timestamp();
+    audit_logs
+        .entries
+        .into_iter()
+        .find(|entry| {
+            actions
+                .iter()
+                .any(|action| action.num() == entry.action.num())
+                && now - entry.id.created_at().unix_timestamp() <= AUDIT_WINDOW_SECS
+                && matches(entry)
+        })
+        .map(|entry| AuditEntry {
+            action: entry.action,
+            moderator: entry.user_id,
+            reason: entry.reason,
+            message_id: entry.options.and_then(|options| options.message_id),
+        })
+}
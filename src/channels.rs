@@ -0,0 +1,425 @@
+use crate::audit_log;
+use crate::config::LogCategory;
+use crate::diff::permission_diff;
+use crate::error::LogError;
+use crate::error::LogResult;
+use crate::excerpt;
+use crate::on_off;
+use crate::threads::is_thread;
+use crate::Change;
+use crate::Handler;
+use crate::FIELD_LIMIT;
+use serenity::builder::CreateEmbed;
+use serenity::model::channel::Channel;
+use serenity::model::channel::ChannelCategory;
+use serenity::model::channel::ChannelType;
+use serenity::model::channel::PartialGuildChannel;
+use serenity::model::channel::PermissionOverwrite;
+use serenity::model::channel::PermissionOverwriteType;
+use serenity::model::guild::audit_log::Action;
+use serenity::model::guild::audit_log::ChannelAction;
+use serenity::model::guild::audit_log::ChannelOverwriteAction;
+use serenity::model::guild::audit_log::ThreadAction;
+use serenity::model::guild::Guild;
+use serenity::model::id::ChannelId;
+use serenity::model::id::GuildId;
+use serenity::model::permissions::Permissions;
+use serenity::model::prelude::GuildChannel;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use std::collections::HashMap;
+
+//what is remembered about a channel, category or thread to diff its updates
+#[derive(Clone)]
+pub struct ChannelSnapshot {
+    pub id: ChannelId,
+    pub guild_id: GuildId,
+    pub name: String,
+    pub kind: ChannelType,
+    pub topic: Option<String>,
+    pub slowmode: Option<u64>,
+    pub nsfw: bool,
+    pub parent_id: Option<ChannelId>,
+    pub overwrites: Vec<PermissionOverwrite>,
+    pub archived: bool,
+    pub locked: bool,
+    //names of a forum's tags by id, fetched the first time a post in the
+    //forum is logged and forgotten when the forum is updated
+    pub forum_tags: Option<HashMap<String, String>>,
+}
+
+impl From<&GuildChannel> for ChannelSnapshot {
+    fn from(channel: &GuildChannel) -> Self {
+        ChannelSnapshot {
+            id: channel.id,
+            guild_id: channel.guild_id,
+            name: channel.name.clone(),
+            kind: channel.kind,
+            topic: channel.topic.clone(),
+            slowmode: channel.rate_limit_per_user,
+            nsfw: channel.nsfw,
+            parent_id: channel.parent_id,
+            overwrites: channel.permission_overwrites.clone(),
+            archived: channel
+                .thread_metadata
+                .is_some_and(|metadata| metadata.archived),
+            locked: channel
+                .thread_metadata
+                .is_some_and(|metadata| metadata.locked),
+            forum_tags: None,
+        }
+    }
+}
+
+impl From<&ChannelCategory> for ChannelSnapshot {
+    fn from(category: &ChannelCategory) -> Self {
+        ChannelSnapshot {
+            id: category.id,
+            guild_id: category.guild_id,
+            name: category.name.clone(),
+            kind: category.kind,
+            topic: None,
+            slowmode: None,
+            nsfw: category.nsfw,
+            parent_id: category.parent_id,
+            overwrites: category.permission_overwrites.clone(),
+            archived: false,
+            locked: false,
+            forum_tags: None,
+        }
+    }
+}
+
+impl ChannelSnapshot {
+    pub fn from_channel(channel: &Channel) -> Option<Self> {
+        match channel {
+            Channel::Guild(channel) => Some(ChannelSnapshot::from(channel)),
+            Channel::Category(category) => Some(ChannelSnapshot::from(category)),
+            _ => None,
+        }
+    }
+
+    //what the channel is called in log titles
+    fn noun(&self) -> &'static str {
+        if is_thread(self.kind) {
+            "thread"
+        } else if self.kind == ChannelType::Category {
+            "category"
+        } else {
+            "channel"
+        }
+    }
+}
+
+//the audit log actions that could have made this change to a channel
+fn audit_actions(change: Change, is_thread: bool) -> Vec<Action> {
+    if is_thread {
+        return vec![Action::Thread(match change {
+            Change::CREATED => ThreadAction::Create,
+            Change::UPDATED => ThreadAction::Update,
+            Change::DELETED => ThreadAction::Delete,
+        })];
+    }
+    match change {
+        Change::CREATED => vec![Action::Channel(ChannelAction::Create)],
+        Change::UPDATED => vec![
+            Action::Channel(ChannelAction::Update),
+            Action::ChannelOverwrite(ChannelOverwriteAction::Create),
+            Action::ChannelOverwrite(ChannelOverwriteAction::Update),
+            Action::ChannelOverwrite(ChannelOverwriteAction::Delete),
+        ],
+        Change::DELETED => vec![Action::Channel(ChannelAction::Delete)],
+    }
+}
+
+impl Handler {
+    //remember every channel and thread of a guild once it becomes available
+    pub fn load_channels(&self, guild: &Guild) {
+        let mut channels = self.channels.lock().unwrap();
+        for (c_id, channel) in &guild.channels {
+            if let Some(snapshot) = ChannelSnapshot::from_channel(channel) {
+                channels.insert(*c_id, snapshot);
+            }
+        }
+        for thread in &guild.threads {
+            channels.insert(thread.id, ChannelSnapshot::from(thread));
+        }
+    }
+
+    pub async fn log_channel_create(
+        &self,
+        ctx: &Context,
+        created: ChannelSnapshot,
+    ) -> LogResult<()> {
+        self.channels
+            .lock()
+            .unwrap()
+            .insert(created.id, created.clone());
+
+        //ignore channel changes if logging not set up
+        if !self.is_logged(created.guild_id, LogCategory::CHANNELS) {
+            return Ok(());
+        }
+
+        let mut embed = self.channel_embed(ctx, &created, Change::CREATED).await;
+        embed.field("type:", created.kind.name().replace('_', " "), true);
+        if let Some(parent_id) = created.parent_id {
+            embed.field(parent_label(&created), format!("<#{parent_id}>"), true);
+        }
+        self.log_event(ctx, created.guild_id, LogCategory::CHANNELS, embed)
+            .await
+    }
+
+    pub async fn log_channel_update(
+        &self,
+        ctx: &Context,
+        updated: ChannelSnapshot,
+    ) -> LogResult<()> {
+        let previous = self
+            .channels
+            .lock()
+            .unwrap()
+            .insert(updated.id, updated.clone());
+        //nothing to compare against for channels that were never cached
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+
+        let changes = channel_changes(&previous, &updated);
+        if changes.is_empty() || !self.is_logged(updated.guild_id, LogCategory::CHANNELS) {
+            return Ok(());
+        }
+
+        let mut embed = self.channel_embed(ctx, &updated, Change::UPDATED).await;
+        for (name, value) in changes {
+            embed.field(name, value, false);
+        }
+        self.log_event(ctx, updated.guild_id, LogCategory::CHANNELS, embed)
+            .await
+    }
+
+    pub async fn log_channel_delete(
+        &self,
+        ctx: &Context,
+        deleted: ChannelSnapshot,
+    ) -> LogResult<()> {
+        self.channels.lock().unwrap().remove(&deleted.id);
+        self.forget_log_channel(ctx, deleted.guild_id, deleted.id)
+            .await?;
+        if !self.is_logged(deleted.guild_id, LogCategory::CHANNELS) {
+            return Ok(());
+        }
+
+        let mut embed = self.channel_embed(ctx, &deleted, Change::DELETED).await;
+        if let Some(parent_id) = deleted.parent_id {
+            embed.field(parent_label(&deleted), format!("<#{parent_id}>"), true);
+        }
+        self.log_event(ctx, deleted.guild_id, LogCategory::CHANNELS, embed)
+            .await
+    }
+
+    //threads are only sent as ids when they are deleted
+    pub async fn log_thread_delete(
+        &self,
+        ctx: &Context,
+        thread: PartialGuildChannel,
+    ) -> LogResult<()> {
+        let cached = self.channels.lock().unwrap().get(&thread.id).cloned();
+        let deleted = match cached {
+            Some(cached) => cached,
+            None => ChannelSnapshot {
+                id: thread.id,
+                guild_id: thread.guild_id,
+                name: "unknown".to_owned(),
+                kind: thread.kind,
+                topic: None,
+                slowmode: None,
+                nsfw: false,
+                parent_id: Some(thread.parent_id),
+                overwrites: vec![],
+                archived: false,
+                locked: false,
+                forum_tags: None,
+            },
+        };
+        self.log_channel_delete(ctx, deleted).await
+    }
+
+    //stop sending logs to a log channel that was deleted
+    async fn forget_log_channel(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        c_id: ChannelId,
+    ) -> LogResult<()> {
+        let forgotten = match self.map.lock().unwrap().get_mut(&g_id.to_string()) {
+            Some(config) => {
+                let was_main = config.channel == Some(c_id.0);
+                if was_main {
+                    config.channel = None;
+                }
+                let route_count = config.routes.len();
+                config.routes.retain(|_, routed_id| *routed_id != c_id.0);
+                was_main || config.routes.len() != route_count
+            }
+            None => false,
+        };
+        if !forgotten {
+            return Ok(());
+        }
+
+        self.save_config(g_id)?;
+        self.report_error(
+            ctx,
+            Some(g_id),
+            "channel_delete",
+            LogError::LogChannelMissing(c_id),
+        )
+        .await;
+        Ok(())
+    }
+
+    //embed about a channel with whoever changed it according to the audit log
+    async fn channel_embed(
+        &self,
+        ctx: &Context,
+        channel: &ChannelSnapshot,
+        change: Change,
+    ) -> CreateEmbed {
+        let title = format!("{} {}", channel.noun(), change.name());
+        let mention = match change {
+            Change::DELETED => format!("#{}", channel.name),
+            _ => format!("<#{}> (#{})", channel.id, channel.name),
+        };
+
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(title)
+            .field(format!("{}:", channel.noun()), mention, true)
+            .footer(|footer| footer.text(format!("channel id: {}", channel.id)))
+            .timestamp(Timestamp::now())
+            .color(change.color());
+
+        let actions = audit_actions(change, is_thread(channel.kind));
+        if let Some(entry) =
+            audit_log::find_entry(ctx, channel.guild_id, &actions, channel.id.0).await
+        {
+            embed.field("by:", entry.moderator_mention(), true);
+        }
+        embed
+    }
+}
+
+//threads live in a channel, everything else in a category
+fn parent_label(channel: &ChannelSnapshot) -> &'static str {
+    if is_thread(channel.kind) {
+        "in channel:"
+    } else {
+        "in category:"
+    }
+}
+
+//the fields describing what changed between two versions of a channel
+fn channel_changes(before: &ChannelSnapshot, after: &ChannelSnapshot) -> Vec<(String, String)> {
+    let mut changes: Vec<(String, String)> = vec![];
+    if before.name != after.name {
+        changes.push((
+            "name:".to_owned(),
+            format!("#{} → #{}", before.name, after.name),
+        ));
+    }
+    if before.topic != after.topic {
+        let topic = |topic: &Option<String>| match topic.as_deref() {
+            Some("") | None => "none".to_owned(),
+            Some(topic) => excerpt(topic, FIELD_LIMIT / 2 - 3),
+        };
+        changes.push(("topic before:".to_owned(), topic(&before.topic)));
+        changes.push(("topic after:".to_owned(), topic(&after.topic)));
+    }
+    if before.slowmode.unwrap_or(0) != after.slowmode.unwrap_or(0) {
+        changes.push((
+            "slowmode:".to_owned(),
+            format!(
+                "{}s → {}s",
+                before.slowmode.unwrap_or(0),
+                after.slowmode.unwrap_or(0)
+            ),
+        ));
+    }
+    if before.nsfw != after.nsfw {
+        changes.push(("nsfw:".to_owned(), on_off(before.nsfw, after.nsfw)));
+    }
+    if before.parent_id != after.parent_id {
+        let parent = |parent_id: Option<ChannelId>| match parent_id {
+            Some(parent_id) => format!("<#{parent_id}>"),
+            None => "none".to_owned(),
+        };
+        changes.push((
+            parent_label(after).to_owned(),
+            format!("{} → {}", parent(before.parent_id), parent(after.parent_id)),
+        ));
+    }
+    if before.archived != after.archived {
+        changes.push((
+            "archived:".to_owned(),
+            on_off(before.archived, after.archived),
+        ));
+    }
+    if before.locked != after.locked {
+        changes.push(("locked:".to_owned(), on_off(before.locked, after.locked)));
+    }
+
+    let overwrites = overwrite_changes(&before.overwrites, &after.overwrites);
+    if !overwrites.is_empty() {
+        changes.push((
+            "permissions:".to_owned(),
+            excerpt(&overwrites.join("\n"), FIELD_LIMIT - 3),
+        ));
+    }
+    changes
+}
+
+//describe the permission overwrites that were added, removed or changed
+fn overwrite_changes(before: &[PermissionOverwrite], after: &[PermissionOverwrite]) -> Vec<String> {
+    let mut changes: Vec<String> = vec![];
+    for overwrite in after {
+        let target = overwrite_target(&overwrite.kind);
+        let previous = before
+            .iter()
+            .find(|previous| previous.kind == overwrite.kind);
+        let (allow, deny) = match previous {
+            Some(previous) if previous == overwrite => continue,
+            Some(previous) => (previous.allow, previous.deny),
+            None => {
+                changes.push(format!("added overwrite for {target}"));
+                (Permissions::empty(), Permissions::empty())
+            }
+        };
+        let allowed = permission_diff(allow, overwrite.allow);
+        if !allowed.is_empty() {
+            changes.push(format!("{target} allowed: {allowed}"));
+        }
+        let denied = permission_diff(deny, overwrite.deny);
+        if !denied.is_empty() {
+            changes.push(format!("{target} denied: {denied}"));
+        }
+    }
+    for overwrite in before {
+        if !after.iter().any(|current| current.kind == overwrite.kind) {
+            changes.push(format!(
+                "removed overwrite for {}",
+                overwrite_target(&overwrite.kind)
+            ));
+        }
+    }
+    changes
+}
+
+fn overwrite_target(kind: &PermissionOverwriteType) -> String {
+    match kind {
+        PermissionOverwriteType::Member(user_id) => format!("<@{user_id}>"),
+        PermissionOverwriteType::Role(role_id) => format!("<@&{role_id}>"),
+        _ => "unknown".to_owned(),
+    }
+}
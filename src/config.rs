@@ -0,0 +1,174 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+//groups of events that can be sent to their own log channel
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum LogCategory {
+    MESSAGES,
+    EDITS,
+    MEMBERS,
+    MODERATION,
+    CHANNELS,
+    ROLES,
+    VOICE,
+    REACTIONS,
+    SERVER,
+    INVITES,
+}
+
+impl LogCategory {
+    pub const ALL: [LogCategory; 10] = [
+        LogCategory::MESSAGES,
+        LogCategory::EDITS,
+        LogCategory::MEMBERS,
+        LogCategory::MODERATION,
+        LogCategory::CHANNELS,
+        LogCategory::ROLES,
+        LogCategory::VOICE,
+        LogCategory::REACTIONS,
+        LogCategory::SERVER,
+        LogCategory::INVITES,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogCategory::MESSAGES => "messages",
+            LogCategory::EDITS => "edits",
+            LogCategory::MEMBERS => "members",
+            LogCategory::MODERATION => "moderation",
+            LogCategory::CHANNELS => "channels",
+            LogCategory::ROLES => "roles",
+            LogCategory::VOICE => "voice",
+            LogCategory::REACTIONS => "reactions",
+            LogCategory::SERVER => "server",
+            LogCategory::INVITES => "invites",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            LogCategory::MESSAGES => "posted messages",
+            LogCategory::EDITS => "edited, deleted and pinned messages",
+            LogCategory::MEMBERS => "members joining, leaving and changing",
+            LogCategory::MODERATION => "bans, kicks and timeouts",
+            LogCategory::CHANNELS => "channels, categories and threads changing",
+            LogCategory::ROLES => "roles and their permissions changing",
+            LogCategory::VOICE => "voice channel activity",
+            LogCategory::REACTIONS => "reactions being added and removed",
+            LogCategory::SERVER => "server settings, emojis and stickers changing",
+            LogCategory::INVITES => "invites being created and deleted",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LogCategory> {
+        LogCategory::ALL
+            .into_iter()
+            .find(|category| category.name() == name)
+    }
+}
+
+//noisy events that are only logged once a guild turns them on
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Toggle {
+    VOICE,
+    REACTIONS,
+    THREADS,
+}
+
+impl Toggle {
+    pub const ALL: [Toggle; 3] = [Toggle::VOICE, Toggle::REACTIONS, Toggle::THREADS];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Toggle::VOICE => "voice",
+            Toggle::REACTIONS => "reactions",
+            Toggle::THREADS => "threads",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Toggle::VOICE => "voice channel activity",
+            Toggle::REACTIONS => "reactions",
+            Toggle::THREADS => "messages in threads and forum posts",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Toggle> {
+        Toggle::ALL.into_iter().find(|toggle| toggle.name() == name)
+    }
+}
+
+//where a guild's logs are sent
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(from = "SavedConfig")]
+pub struct GuildConfig {
+    //channel for every category without its own channel
+    pub channel: Option<u64>,
+    pub routes: HashMap<LogCategory, u64>,
+    //channels and categories whose messages are not logged
+    pub ignored_channels: HashSet<u64>,
+    //users and roles whose messages are not logged
+    pub ignored_users: HashSet<u64>,
+    pub ignored_roles: HashSet<u64>,
+    //whether messages from bots and webhooks are not logged
+    pub ignore_bots: bool,
+    //noisy events the guild turned on
+    pub toggles: HashSet<Toggle>,
+}
+
+impl GuildConfig {
+    //get the channel that events of this category are logged in
+    pub fn channel_for(&self, category: LogCategory) -> Option<u64> {
+        self.routes.get(&category).copied().or(self.channel)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channel.is_none()
+            && self.routes.is_empty()
+            && self.ignored_channels.is_empty()
+            && self.ignored_users.is_empty()
+            && self.ignored_roles.is_empty()
+            && !self.ignore_bots
+            && self.toggles.is_empty()
+    }
+
+    pub fn is_enabled(&self, toggle: Toggle) -> bool {
+        self.toggles.contains(&toggle)
+    }
+}
+
+//older versions of servers.json only stored one channel id per guild
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedConfig {
+    Channel(u64),
+    Config {
+        channel: Option<u64>,
+        #[serde(default)]
+        routes: HashMap<LogCategory, u64>,
+    },
+}
+
+impl From<SavedConfig> for GuildConfig {
+    fn from(saved: SavedConfig) -> Self {
+        match saved {
+            //older versions logged threads like every other channel
+            SavedConfig::Channel(channel) => GuildConfig {
+                channel: Some(channel),
+                toggles: HashSet::from([Toggle::THREADS]),
+                ..Default::default()
+            },
+            SavedConfig::Config { channel, routes } => GuildConfig {
+                channel,
+                routes,
+                toggles: HashSet::from([Toggle::THREADS]),
+                ..Default::default()
+            },
+        }
+    }
+}
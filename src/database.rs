@@ -0,0 +1,496 @@
+use crate::archive::ArchivedAttachment;
+use crate::config::GuildConfig;
+use crate::config::LogCategory;
+use crate::config::Toggle;
+use crate::error::LogError;
+use crate::error::LogResult;
+use rusqlite::params;
+use rusqlite::Connection;
+use rusqlite::Transaction;
+use serde::Deserialize;
+use serenity::model::id::AttachmentId;
+use serenity::model::id::ChannelId;
+use serenity::model::id::MessageId;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+//every schema change gets a new entry, the schema version stored in the
+//database is the number of migrations that have been applied
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE guilds (
+        guild_id INTEGER PRIMARY KEY,
+        log_channel INTEGER
+    );
+    CREATE TABLE log_routes (
+        guild_id INTEGER NOT NULL REFERENCES guilds(guild_id) ON DELETE CASCADE,
+        category TEXT NOT NULL,
+        channel_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, category)
+    );
+",
+    "
+    CREATE TABLE ignored_channels (
+        guild_id INTEGER NOT NULL REFERENCES guilds(guild_id) ON DELETE CASCADE,
+        channel_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, channel_id)
+    );
+",
+    "
+    CREATE TABLE ignored_users (
+        guild_id INTEGER NOT NULL REFERENCES guilds(guild_id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, user_id)
+    );
+    CREATE TABLE ignored_roles (
+        guild_id INTEGER NOT NULL REFERENCES guilds(guild_id) ON DELETE CASCADE,
+        role_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, role_id)
+    );
+    ALTER TABLE guilds ADD COLUMN ignore_bots INTEGER NOT NULL DEFAULT 0;
+",
+    "
+    CREATE TABLE enabled_toggles (
+        guild_id INTEGER NOT NULL REFERENCES guilds(guild_id) ON DELETE CASCADE,
+        toggle TEXT NOT NULL,
+        PRIMARY KEY (guild_id, toggle)
+    );
+",
+    //threads used to be logged like every other channel
+    "
+    INSERT INTO enabled_toggles (guild_id, toggle) SELECT guild_id, 'threads' FROM guilds;
+",
+    "
+    CREATE TABLE archived_attachments (
+        attachment_id INTEGER PRIMARY KEY,
+        message_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        filename TEXT NOT NULL,
+        content_type TEXT,
+        size INTEGER NOT NULL,
+        sha256 TEXT NOT NULL
+    );
+    CREATE INDEX archived_attachments_message ON archived_attachments (message_id);
+",
+    //attachments archived before this are kept for the retention period
+    //from when the migration ran
+    "
+    ALTER TABLE archived_attachments ADD COLUMN archived_at INTEGER NOT NULL DEFAULT 0;
+    UPDATE archived_attachments SET archived_at = strftime('%s', 'now');
+    CREATE INDEX archived_attachments_age ON archived_attachments (archived_at);
+",
+];
+
+#[derive(Deserialize, Debug)]
+struct SaveMap {
+    #[serde(flatten)]
+    map: HashMap<String, GuildConfig>,
+}
+
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    //open the database, bringing its schema up to date and importing the
+    //json file older versions stored guild configs in
+    pub fn open(path: &str, json_path: &str, imported_json_path: &str) -> LogResult<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let mut database = Database { conn };
+        let version = database.schema_version()?;
+        database.migrate(version)?;
+
+        //the old json file is only moved aside once it was imported, so an
+        //import that failed is tried again on the next start
+        database.import_json(json_path, imported_json_path)?;
+        Ok(database)
+    }
+
+    fn schema_version(&self) -> rusqlite::Result<usize> {
+        self.conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+    }
+
+    //apply every migration newer than the current schema version
+    fn migrate(&mut self, version: usize) -> rusqlite::Result<()> {
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", index + 1)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    //copy the guild configs from servers.json and set the file aside
+    fn import_json(&mut self, json_path: &str, imported_json_path: &str) -> LogResult<()> {
+        if !Path::new(json_path).exists() {
+            return Ok(());
+        }
+
+        let contents = fs::read_to_string(json_path)
+            .map_err(|why| LogError::Import(format!("unable to read {json_path}: {why}")))?;
+        //every guild is imported or none are
+        let tx = self.conn.transaction()?;
+        if !contents.is_empty() {
+            let save_map = serde_json::from_str::<SaveMap>(&contents)
+                .map_err(|why| LogError::Import(format!("unable to parse {json_path}: {why}")))?;
+            for (g_id_str, config) in save_map.map {
+                let g_id: u64 = g_id_str.parse().map_err(|_| {
+                    LogError::Import(format!("invalid guild id {g_id_str} in {json_path}"))
+                })?;
+                write_config(&tx, g_id, &config)?;
+            }
+        }
+        tx.commit()?;
+
+        fs::rename(json_path, imported_json_path).map_err(|why| {
+            LogError::Import(format!("unable to move {json_path} out of the way: {why}"))
+        })?;
+        println!("imported {json_path} into the database");
+        Ok(())
+    }
+
+    //read the config of every guild
+    pub fn load_configs(&self) -> rusqlite::Result<HashMap<String, GuildConfig>> {
+        let mut configs: HashMap<String, GuildConfig> = HashMap::new();
+
+        let mut guilds = self
+            .conn
+            .prepare("SELECT guild_id, log_channel, ignore_bots FROM guilds")?;
+        let rows = guilds.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, bool>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (g_id, channel, ignore_bots) = row?;
+            let config = configs.entry(g_id.to_string()).or_default();
+            config.channel = channel.map(|c_id| c_id as u64);
+            config.ignore_bots = ignore_bots;
+        }
+
+        let mut routes = self
+            .conn
+            .prepare("SELECT guild_id, category, channel_id FROM log_routes")?;
+        let rows = routes.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (g_id, category, c_id) = row?;
+            //categories from newer versions are skipped
+            if let Some(category) = LogCategory::from_name(&category) {
+                let config = configs.entry(g_id.to_string()).or_default();
+                config.routes.insert(category, c_id as u64);
+            }
+        }
+
+        let mut toggles = self
+            .conn
+            .prepare("SELECT guild_id, toggle FROM enabled_toggles")?;
+        let rows = toggles.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (g_id, toggle) = row?;
+            //toggles from newer versions are skipped
+            if let Some(toggle) = Toggle::from_name(&toggle) {
+                let config = configs.entry(g_id.to_string()).or_default();
+                config.toggles.insert(toggle);
+            }
+        }
+
+        for (g_id, c_id) in self.load_ignore_list("ignored_channels", "channel_id")? {
+            let config = configs.entry(g_id.to_string()).or_default();
+            config.ignored_channels.insert(c_id);
+        }
+        for (g_id, user_id) in self.load_ignore_list("ignored_users", "user_id")? {
+            let config = configs.entry(g_id.to_string()).or_default();
+            config.ignored_users.insert(user_id);
+        }
+        for (g_id, role_id) in self.load_ignore_list("ignored_roles", "role_id")? {
+            let config = configs.entry(g_id.to_string()).or_default();
+            config.ignored_roles.insert(role_id);
+        }
+
+        Ok(configs)
+    }
+
+    //replace the stored config of a guild
+    pub fn save_config(&mut self, g_id: u64, config: &GuildConfig) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        write_config(&tx, g_id, config)?;
+        tx.commit()
+    }
+
+    //read every (guild id, ignored id) pair of an ignore list table
+    fn load_ignore_list(&self, table: &str, column: &str) -> rusqlite::Result<Vec<(u64, u64)>> {
+        let mut ignored = self
+            .conn
+            .prepare(&format!("SELECT guild_id, {column} FROM {table}"))?;
+        let rows = ignored.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64))
+        })?;
+        rows.collect()
+    }
+
+    //forget everything about a guild, returning whether it was stored
+    pub fn delete_guild(&self, g_id: u64) -> rusqlite::Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM guilds WHERE guild_id = ?1",
+            params![g_id as i64],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    //remember which message an archived file came from
+    pub fn insert_attachment(&self, archived: &ArchivedAttachment) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO archived_attachments
+                (attachment_id, message_id, channel_id, filename, content_type, size, sha256,
+                archived_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, strftime('%s', 'now'))",
+            params![
+                archived.attachment_id.0 as i64,
+                archived.message_id.0 as i64,
+                archived.channel_id.0 as i64,
+                archived.filename,
+                archived.content_type,
+                archived.size as i64,
+                archived.sha256
+            ],
+        )?;
+        Ok(())
+    }
+
+    //read the archived attachments of a message
+    pub fn message_attachments(
+        &self,
+        message_id: u64,
+    ) -> rusqlite::Result<Vec<ArchivedAttachment>> {
+        let mut attachments = self.conn.prepare(
+            "SELECT attachment_id, message_id, channel_id, filename, content_type, size, sha256
+            FROM archived_attachments WHERE message_id = ?1 ORDER BY attachment_id",
+        )?;
+        let rows = attachments.query_map(params![message_id as i64], |row| {
+            Ok(ArchivedAttachment {
+                attachment_id: AttachmentId(row.get::<_, i64>(0)? as u64),
+                message_id: MessageId(row.get::<_, i64>(1)? as u64),
+                channel_id: ChannelId(row.get::<_, i64>(2)? as u64),
+                filename: row.get(3)?,
+                content_type: row.get(4)?,
+                size: row.get::<_, i64>(5)? as u64,
+                sha256: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    //forget attachments archived before a unix timestamp, returning the
+    //hashes of files that no message uses anymore
+    pub fn prune_attachments(&mut self, before: i64) -> rusqlite::Result<Vec<String>> {
+        let tx = self.conn.transaction()?;
+        let expired: Vec<String> = {
+            let mut expired = tx.prepare(
+                "SELECT DISTINCT sha256 FROM archived_attachments WHERE archived_at < ?1",
+            )?;
+            let rows = expired.query_map(params![before], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        tx.execute(
+            "DELETE FROM archived_attachments WHERE archived_at < ?1",
+            params![before],
+        )?;
+
+        let mut unused: Vec<String> = vec![];
+        for sha256 in expired {
+            let still_used: bool = tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM archived_attachments WHERE sha256 = ?1)",
+                params![sha256],
+                |row| row.get(0),
+            )?;
+            if !still_used {
+                unused.push(sha256);
+            }
+        }
+        tx.commit()?;
+        Ok(unused)
+    }
+}
+
+//write the config of a guild, replacing what was stored
+fn write_config(tx: &Transaction, g_id: u64, config: &GuildConfig) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO guilds (guild_id, log_channel, ignore_bots) VALUES (?1, ?2, ?3)
+        ON CONFLICT(guild_id) DO UPDATE SET
+            log_channel = excluded.log_channel,
+            ignore_bots = excluded.ignore_bots",
+        params![
+            g_id as i64,
+            config.channel.map(|c_id| c_id as i64),
+            config.ignore_bots
+        ],
+    )?;
+    tx.execute(
+        "DELETE FROM log_routes WHERE guild_id = ?1",
+        params![g_id as i64],
+    )?;
+    for (category, c_id) in &config.routes {
+        tx.execute(
+            "INSERT INTO log_routes (guild_id, category, channel_id) VALUES (?1, ?2, ?3)",
+            params![g_id as i64, category.name(), *c_id as i64],
+        )?;
+    }
+    tx.execute(
+        "DELETE FROM enabled_toggles WHERE guild_id = ?1",
+        params![g_id as i64],
+    )?;
+    for toggle in &config.toggles {
+        tx.execute(
+            "INSERT INTO enabled_toggles (guild_id, toggle) VALUES (?1, ?2)",
+            params![g_id as i64, toggle.name()],
+        )?;
+    }
+    save_ignore_list(
+        tx,
+        g_id,
+        "ignored_channels",
+        "channel_id",
+        &config.ignored_channels,
+    )?;
+    save_ignore_list(tx, g_id, "ignored_users", "user_id", &config.ignored_users)?;
+    save_ignore_list(tx, g_id, "ignored_roles", "role_id", &config.ignored_roles)?;
+    Ok(())
+}
+
+//replace the ids of a guild's ignore list table
+fn save_ignore_list(
+    tx: &Transaction,
+    g_id: u64,
+    table: &str,
+    column: &str,
+    ids: &HashSet<u64>,
+) -> rusqlite::Result<()> {
+    tx.execute(
+        &format!("DELETE FROM {table} WHERE guild_id = ?1"),
+        params![g_id as i64],
+    )?;
+    for id in ids {
+        tx.execute(
+            &format!("INSERT INTO {table} (guild_id, {column}) VALUES (?1, ?2)"),
+            params![g_id as i64, *id as i64],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    //a fresh directory for the files of one test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("discord-logger-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn open(dir: &Path) -> LogResult<Database> {
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+        Database::open(
+            &path("logger.db"),
+            &path("servers.json"),
+            &path("servers.json.imported"),
+        )
+    }
+
+    #[test]
+    fn migrations_reach_latest_version() {
+        let dir = test_dir("migrations");
+        let database = open(&dir).unwrap();
+        assert_eq!(database.schema_version().unwrap(), MIGRATIONS.len());
+        assert!(database.load_configs().unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn imports_legacy_json() {
+        let dir = test_dir("import");
+        fs::write(
+            dir.join("servers.json"),
+            r#"{"1": 10, "2": {"channel": 20, "routes": {"edits": 21}}}"#,
+        )
+        .unwrap();
+
+        let configs = open(&dir).unwrap().load_configs().unwrap();
+        assert_eq!(configs["1"].channel, Some(10));
+        assert_eq!(configs["2"].channel, Some(20));
+        assert_eq!(configs["2"].channel_for(LogCategory::EDITS), Some(21));
+        assert_eq!(configs["2"].channel_for(LogCategory::MESSAGES), Some(20));
+        //threads were logged like every other channel before the toggle
+        assert!(configs
+            .values()
+            .all(|config| config.is_enabled(Toggle::THREADS)));
+
+        assert!(!dir.join("servers.json").exists());
+        assert!(dir.join("servers.json.imported").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_import_is_retried() {
+        let dir = test_dir("retry");
+        fs::write(dir.join("servers.json"), r#"{"1": 10, "2": "#).unwrap();
+        assert!(matches!(open(&dir), Err(LogError::Import(_))));
+        assert!(dir.join("servers.json").exists());
+        assert!(!dir.join("servers.json.imported").exists());
+
+        fs::write(dir.join("servers.json"), r#"{"1": 10, "2": 20}"#).unwrap();
+        let configs = open(&dir).unwrap().load_configs().unwrap();
+        assert_eq!(configs.len(), 2);
+        assert!(!dir.join("servers.json").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn threads_migration_keeps_existing_guilds_logging_threads() {
+        //a database from before the threads toggle with one guild set up
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&MIGRATIONS[..4].concat()).unwrap();
+        conn.pragma_update(None, "user_version", 4).unwrap();
+        conn.execute(
+            "INSERT INTO guilds (guild_id, log_channel) VALUES (1, 10)",
+            [],
+        )
+        .unwrap();
+
+        let mut database = Database { conn };
+        database.migrate(4).unwrap();
+        database
+            .save_config(
+                2,
+                &GuildConfig {
+                    channel: Some(20),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let configs = database.load_configs().unwrap();
+        assert!(configs["1"].is_enabled(Toggle::THREADS));
+        assert!(!configs["2"].is_enabled(Toggle::THREADS));
+    }
+}
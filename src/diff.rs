@@ -0,0 +1,170 @@
+use serenity::model::permissions::Permissions;
+
+//unchanged words kept around each change, longer unchanged runs are collapsed
+const CONTEXT_WORDS: usize = 3;
+//the diff table grows with the product of both word counts, longer messages
+//are only shown before and after
+const DIFF_WORD_LIMIT: usize = 500;
+
+enum WordChange<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+//render the word level changes between two messages as inline markdown,
+//removed words are struck through and added words are bolded. None when
+//either message is too long to diff
+pub fn word_diff(before: &str, after: &str) -> Option<String> {
+    let old_words: Vec<&str> = before.split_whitespace().collect();
+    let new_words: Vec<&str> = after.split_whitespace().collect();
+    if old_words.len() > DIFF_WORD_LIMIT || new_words.len() > DIFF_WORD_LIMIT {
+        return None;
+    }
+    let changes = diff_words(&old_words, &new_words);
+
+    let mut rendered: Vec<String> = vec![];
+    let mut index = 0;
+    while index < changes.len() {
+        match changes[index] {
+            WordChange::Removed(word) => rendered.push(format!("~~{word}~~")),
+            WordChange::Added(word) => rendered.push(format!("**{word}**")),
+            WordChange::Same(_) => {
+                //gather the whole run of unchanged words
+                let at_start = index == 0;
+                let mut run: Vec<&str> = vec![];
+                while let Some(WordChange::Same(word)) = changes.get(index) {
+                    run.push(word);
+                    index += 1;
+                }
+                let at_end = index == changes.len();
+                rendered.extend(collapse_unchanged(&run, at_start, at_end));
+                continue;
+            }
+        }
+        index += 1;
+    }
+    Some(rendered.join(" "))
+}
+
+//keep only the words next to a change from a run of unchanged words
+fn collapse_unchanged(run: &[&str], at_start: bool, at_end: bool) -> Vec<String> {
+    let words: Vec<String> = run.iter().map(|word| word.to_string()).collect();
+
+    //only whitespace changed, there is nothing to collapse around
+    if at_start && at_end {
+        return words;
+    }
+
+    let keep_front = if at_start { 0 } else { CONTEXT_WORDS };
+    let keep_back = if at_end { 0 } else { CONTEXT_WORDS };
+    if words.len() <= keep_front + keep_back + 1 {
+        return words;
+    }
+
+    let mut collapsed = words[..keep_front].to_vec();
+    collapsed.push("…".to_owned());
+    collapsed.extend_from_slice(&words[words.len() - keep_back..]);
+    collapsed
+}
+
+//longest common subsequence over words
+fn diff_words<'a>(old_words: &[&'a str], new_words: &[&'a str]) -> Vec<WordChange<'a>> {
+    let (rows, cols) = (old_words.len(), new_words.len());
+    //lcs[i][j] is the lcs length of old_words[i..] and new_words[j..]
+    let mut lcs = vec![vec![0u32; cols + 1]; rows + 1];
+    for i in (0..rows).rev() {
+        for j in (0..cols).rev() {
+            lcs[i][j] = if old_words[i] == new_words[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < rows && j < cols {
+        if old_words[i] == new_words[j] {
+            changes.push(WordChange::Same(old_words[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            changes.push(WordChange::Removed(old_words[i]));
+            i += 1;
+        } else {
+            changes.push(WordChange::Added(new_words[j]));
+            j += 1;
+        }
+    }
+    changes.extend(old_words[i..].iter().map(|word| WordChange::Removed(word)));
+    changes.extend(new_words[j..].iter().map(|word| WordChange::Added(word)));
+    changes
+}
+
+//list the permissions granted (+) and revoked (-) between two permission sets,
+//empty when nothing changed
+pub fn permission_diff(before: Permissions, after: Permissions) -> String {
+    let granted = (after - before)
+        .get_permission_names()
+        .into_iter()
+        .map(|name| format!("+{name}"));
+    let revoked = (before - after)
+        .get_permission_names()
+        .into_iter()
+        .map(|name| format!("-{name}"));
+    granted.chain(revoked).collect::<Vec<String>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_diff_marks_changes() {
+        assert_eq!(
+            word_diff("the quick brown fox", "the slow brown fox").unwrap(),
+            "the ~~quick~~ **slow** brown fox"
+        );
+    }
+
+    #[test]
+    fn word_diff_collapses_long_unchanged_runs() {
+        let before = "one two three four five six seven eight nine ten";
+        let after = "one two three four five six seven eight nine eleven";
+        assert_eq!(
+            word_diff(before, after).unwrap(),
+            "… seven eight nine ~~ten~~ **eleven**"
+        );
+    }
+
+    #[test]
+    fn permission_diff_lists_granted_and_revoked() {
+        let before = Permissions::SEND_MESSAGES | Permissions::KICK_MEMBERS;
+        let after = Permissions::SEND_MESSAGES | Permissions::BAN_MEMBERS;
+        assert_eq!(
+            permission_diff(before, after),
+            "+Ban Members, -Kick Members"
+        );
+        assert_eq!(permission_diff(after, after), "");
+    }
+
+    #[test]
+    fn word_diff_keeps_whitespace_only_edits() {
+        assert_eq!(
+            word_diff("same  words", "same words\n").unwrap(),
+            "same words"
+        );
+    }
+
+    #[test]
+    fn word_diff_skips_long_messages() {
+        let long = "word ".repeat(DIFF_WORD_LIMIT + 1);
+        assert_eq!(word_diff(&long, "word"), None);
+        assert_eq!(word_diff("word", &long), None);
+
+        let limit = "word ".repeat(DIFF_WORD_LIMIT);
+        assert!(word_diff(&limit, "word").is_some());
+    }
+}
@@ -0,0 +1,88 @@
+use serenity::http::HttpError;
+use serenity::model::id::ChannelId;
+use std::fmt;
+
+//everything that can stop an event from being logged
+#[derive(Debug)]
+pub enum LogError {
+    //the configured log channel was deleted or isn't a server channel
+    LogChannelMissing(ChannelId),
+    //MessageLogger can't see or send messages in the log channel
+    MissingPermissions(ChannelId),
+    //a slash command was used outside of a server or without its options
+    InvalidCommand(String),
+    Discord(Box<serenity::Error>),
+    Database(rusqlite::Error),
+    //servers.json from older versions couldn't be read
+    Import(String),
+}
+
+pub type LogResult<T> = Result<T, LogError>;
+
+impl LogError {
+    //sort out errors from fetching or sending to the log channel
+    pub fn from_log_channel(why: serenity::Error, c_id: ChannelId) -> Self {
+        if let serenity::Error::Http(http_error) = &why {
+            if let HttpError::UnsuccessfulRequest(response) = http_error.as_ref() {
+                match response.status_code.as_u16() {
+                    403 => return LogError::MissingPermissions(c_id),
+                    404 => return LogError::LogChannelMissing(c_id),
+                    _ => {}
+                }
+            }
+        }
+        LogError::from(why)
+    }
+
+    //the log channel of problems that only the guild can fix
+    pub fn log_channel(&self) -> Option<ChannelId> {
+        match self {
+            LogError::LogChannelMissing(c_id) | LogError::MissingPermissions(c_id) => Some(*c_id),
+            _ => None,
+        }
+    }
+
+    //what the guild is told about problems that only it can fix
+    pub fn guild_warning(&self) -> Option<String> {
+        match self {
+            LogError::LogChannelMissing(c_id) => Some(format!(
+                "MessageLogger can't find its log channel <#{c_id}> anymore! \
+                do /setuplogging in the channel logs should go to"
+            )),
+            LogError::MissingPermissions(c_id) => Some(format!(
+                "MessageLogger is missing permissions in its log channel <#{c_id}>! \
+                it needs to view the channel, send messages, embed links and attach files"
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogError::LogChannelMissing(c_id) => write!(f, "log channel {c_id} is missing"),
+            LogError::MissingPermissions(c_id) => {
+                write!(f, "missing permissions in log channel {c_id}")
+            }
+            LogError::InvalidCommand(why) => write!(f, "invalid command: {why}"),
+            LogError::Discord(why) => write!(f, "discord error: {why}"),
+            LogError::Database(why) => write!(f, "database error: {why}"),
+            LogError::Import(why) => write!(f, "unable to import the json file: {why}"),
+        }
+    }
+}
+
+impl std::error::Error for LogError {}
+
+impl From<serenity::Error> for LogError {
+    fn from(why: serenity::Error) -> Self {
+        LogError::Discord(Box::new(why))
+    }
+}
+
+impl From<rusqlite::Error> for LogError {
+    fn from(why: rusqlite::Error) -> Self {
+        LogError::Database(why)
+    }
+}
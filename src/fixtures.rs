@@ -0,0 +1,15 @@
+use serde_json::json;
+use serenity::model::channel::Attachment;
+
+//attachment as discord sends it, the urls are never fetched
+pub fn attachment(filename: &str, content_type: Option<&str>, size: u64) -> Attachment {
+    serde_json::from_value(json!({
+        "id": "1",
+        "filename": filename,
+        "size": size,
+        "url": format!("https://cdn.discordapp.com/attachments/1/1/{filename}"),
+        "proxy_url": format!("https://media.discordapp.net/attachments/1/1/{filename}"),
+        "content_type": content_type,
+    }))
+    .unwrap()
+}
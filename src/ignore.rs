@@ -0,0 +1,361 @@
+use crate::channels::ChannelSnapshot;
+use crate::command_guild;
+use crate::config::GuildConfig;
+use crate::error::LogError;
+use crate::error::LogResult;
+use crate::respond;
+use crate::threads::is_thread;
+use crate::Handler;
+use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::application_command::CommandDataOption;
+use serenity::model::channel::Channel;
+use serenity::model::id::RoleId;
+use serenity::model::permissions::Permissions;
+use serenity::model::prelude::GuildChannel;
+use serenity::model::user::User;
+use serenity::prelude::*;
+use std::collections::HashSet;
+
+const IGNORE_BOTS: &str = "ignorebots";
+
+const ADD_SUBCOMMAND: &str = "add";
+const LIST_SUBCOMMAND: &str = "list";
+const ENABLED_OPTION: &str = "enabled";
+
+//the kinds of things a guild can stop logging messages from
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
+enum IgnoreList {
+    CHANNELS,
+    USERS,
+    ROLES,
+}
+
+impl IgnoreList {
+    const ALL: [IgnoreList; 3] = [IgnoreList::CHANNELS, IgnoreList::USERS, IgnoreList::ROLES];
+
+    //name of the command that adds to and lists the ignore list
+    fn ignore_command(&self) -> &'static str {
+        match self {
+            IgnoreList::CHANNELS => "ignorechannel",
+            IgnoreList::USERS => "ignoreuser",
+            IgnoreList::ROLES => "ignorerole",
+        }
+    }
+
+    //name of the command that removes from the ignore list
+    fn unignore_command(&self) -> &'static str {
+        match self {
+            IgnoreList::CHANNELS => "unignorechannel",
+            IgnoreList::USERS => "unignoreuser",
+            IgnoreList::ROLES => "unignorerole",
+        }
+    }
+
+    fn option_name(&self) -> &'static str {
+        match self {
+            IgnoreList::CHANNELS => "channel",
+            IgnoreList::USERS => "user",
+            IgnoreList::ROLES => "role",
+        }
+    }
+
+    fn option_kind(&self) -> CommandOptionType {
+        match self {
+            IgnoreList::CHANNELS => CommandOptionType::Channel,
+            IgnoreList::USERS => CommandOptionType::User,
+            IgnoreList::ROLES => CommandOptionType::Role,
+        }
+    }
+
+    //what is being ignored, as used in command descriptions
+    fn description(&self) -> &'static str {
+        match self {
+            IgnoreList::CHANNELS => "a channel or category",
+            IgnoreList::USERS => "a user",
+            IgnoreList::ROLES => "members with a role",
+        }
+    }
+
+    fn mention(&self, id: u64) -> String {
+        match self {
+            IgnoreList::CHANNELS => format!("<#{id}>"),
+            IgnoreList::USERS => format!("<@{id}>"),
+            IgnoreList::ROLES => format!("<@&{id}>"),
+        }
+    }
+
+    fn ids<'a>(&self, config: &'a mut GuildConfig) -> &'a mut HashSet<u64> {
+        match self {
+            IgnoreList::CHANNELS => &mut config.ignored_channels,
+            IgnoreList::USERS => &mut config.ignored_users,
+            IgnoreList::ROLES => &mut config.ignored_roles,
+        }
+    }
+
+    //find the list a slash command manages and whether it removes from it
+    fn from_command(command_name: &str) -> Option<(IgnoreList, bool)> {
+        IgnoreList::ALL.into_iter().find_map(|list| {
+            if command_name == list.ignore_command() {
+                Some((list, false))
+            } else if command_name == list.unignore_command() {
+                Some((list, true))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+pub fn is_ignore_command(command_name: &str) -> bool {
+    command_name == IGNORE_BOTS || IgnoreList::from_command(command_name).is_some()
+}
+
+pub async fn register_commands(ctx: &Context) {
+    for list in IgnoreList::ALL {
+        let _ignore = Command::create_global_application_command(ctx, |command| {
+            command.name(list.ignore_command());
+            command.description(format!("stop logging messages from {}", list.description()));
+            command.create_option(|subcommand| {
+                subcommand
+                    .name(ADD_SUBCOMMAND)
+                    .description(format!("stop logging messages from {}", list.description()))
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|option| {
+                        option
+                            .name(list.option_name())
+                            .description(format!("the {} to ignore", list.option_name()))
+                            .kind(list.option_kind())
+                            .required(true)
+                    })
+            });
+            command.create_option(|subcommand| {
+                subcommand
+                    .name(LIST_SUBCOMMAND)
+                    .description(format!("list the ignored {}s", list.option_name()))
+                    .kind(CommandOptionType::SubCommand)
+            });
+            command.default_member_permissions(Permissions::MANAGE_GUILD)
+        })
+        .await;
+
+        let _unignore = Command::create_global_application_command(ctx, |command| {
+            command.name(list.unignore_command());
+            command.description(format!("log messages from {} again", list.description()));
+            command.create_option(|option| {
+                option
+                    .name(list.option_name())
+                    .description(format!("the {} to log again", list.option_name()))
+                    .kind(list.option_kind())
+                    .required(true)
+            });
+            command.default_member_permissions(Permissions::MANAGE_GUILD)
+        })
+        .await;
+    }
+
+    let _ignore_bots = Command::create_global_application_command(ctx, |command| {
+        command.name(IGNORE_BOTS);
+        command.description("stop logging messages from bots and webhooks");
+        command.create_option(|option| {
+            option
+                .name(ENABLED_OPTION)
+                .description("whether messages from bots and webhooks are ignored")
+                .kind(CommandOptionType::Boolean)
+                .required(true)
+        });
+        command.default_member_permissions(Permissions::MANAGE_GUILD)
+    })
+    .await;
+}
+
+impl Handler {
+    //whether messages sent in this channel should not be logged
+    pub async fn is_ignored_channel(
+        &self,
+        ctx: &Context,
+        g_id_str: &String,
+        channel: &GuildChannel,
+    ) -> bool {
+        let ignored = match self.map.lock().unwrap().get(g_id_str) {
+            Some(config) => config.ignored_channels.clone(),
+            None => return false,
+        };
+        if ignored.is_empty() {
+            return false;
+        }
+
+        if ignored.contains(channel.id.as_u64()) {
+            return true;
+        }
+        let parent_id = match channel.parent_id {
+            Some(parent_id) => parent_id,
+            None => return false,
+        };
+        if ignored.contains(parent_id.as_u64()) {
+            return true;
+        }
+
+        //threads are one level deeper, their parent channel is in the category
+        if !is_thread(channel.kind) {
+            return false;
+        }
+        let cached = self
+            .channels
+            .lock()
+            .unwrap()
+            .get(&parent_id)
+            .map(|parent| parent.parent_id);
+        let category_id = match cached {
+            Some(category_id) => category_id,
+            None => match parent_id.to_channel(ctx).await {
+                Ok(Channel::Guild(parent)) => {
+                    self.channels
+                        .lock()
+                        .unwrap()
+                        .insert(parent_id, ChannelSnapshot::from(&parent));
+                    parent.parent_id
+                }
+                _ => return false,
+            },
+        };
+        category_id.is_some_and(|category_id| ignored.contains(category_id.as_u64()))
+    }
+
+    //whether messages from this author should not be logged
+    pub fn is_ignored_author(
+        &self,
+        g_id_str: &String,
+        author: &User,
+        is_webhook: bool,
+        roles: &[RoleId],
+    ) -> bool {
+        let map = self.map.lock().unwrap();
+        let config = match map.get(g_id_str) {
+            Some(config) => config,
+            None => return false,
+        };
+
+        (config.ignore_bots && (author.bot || is_webhook))
+            || config.ignored_users.contains(author.id.as_u64())
+            || roles
+                .iter()
+                .any(|role_id| config.ignored_roles.contains(role_id.as_u64()))
+    }
+
+    //handle the commands that manage ignore lists
+    pub async fn ignore_command(
+        &self,
+        ctx: &Context,
+        slash_command: &ApplicationCommandInteraction,
+    ) -> LogResult<()> {
+        let command_name = slash_command.data.name.as_str();
+        let g_id = command_guild(slash_command)?;
+        let g_id_str = g_id.to_string();
+
+        if command_name == IGNORE_BOTS {
+            let enabled = slash_command
+                .data
+                .options
+                .iter()
+                .find(|option| option.name == ENABLED_OPTION)
+                .and_then(|option| option.value.as_ref())
+                .and_then(|value| value.as_bool())
+                .ok_or_else(|| {
+                    LogError::InvalidCommand(format!("/{IGNORE_BOTS} needs the enabled option"))
+                })?;
+            self.map
+                .lock()
+                .unwrap()
+                .entry(g_id_str.clone())
+                .or_default()
+                .ignore_bots = enabled;
+            self.save_config(g_id)?;
+
+            let response = if enabled {
+                "messages from bots and webhooks will no longer be logged!"
+            } else {
+                "messages from bots and webhooks will be logged again!"
+            };
+            return respond(ctx, slash_command, response.to_owned()).await;
+        }
+
+        let (list, unignore) = match IgnoreList::from_command(command_name) {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        //the unignore commands take the option directly
+        if unignore {
+            let id = id_option(list, &slash_command.data.options)?;
+            let removed = match self.map.lock().unwrap().get_mut(&g_id_str) {
+                Some(config) => list.ids(config).remove(&id),
+                None => false,
+            };
+            self.save_config(g_id)?;
+
+            let response = if removed {
+                format!("messages from {} will be logged again!", list.mention(id))
+            } else {
+                format!("{} is not being ignored!", list.mention(id))
+            };
+            return respond(ctx, slash_command, response).await;
+        }
+
+        let subcommand = slash_command.data.options.first().ok_or_else(|| {
+            LogError::InvalidCommand(format!("/{command_name} needs a subcommand"))
+        })?;
+
+        if subcommand.name == ADD_SUBCOMMAND {
+            let id = id_option(list, &subcommand.options)?;
+            {
+                let mut map = self.map.lock().unwrap();
+                list.ids(map.entry(g_id_str.clone()).or_default())
+                    .insert(id);
+            }
+            self.save_config(g_id)?;
+
+            let response = format!(
+                "messages from {} will no longer be logged!",
+                list.mention(id)
+            );
+            return respond(ctx, slash_command, response).await;
+        }
+
+        if subcommand.name == LIST_SUBCOMMAND {
+            let mut ignored: Vec<u64> = match self.map.lock().unwrap().get_mut(&g_id_str) {
+                Some(config) => list.ids(config).iter().copied().collect(),
+                None => vec![],
+            };
+            ignored.sort();
+
+            let response = if ignored.is_empty() {
+                format!("no {}s are being ignored!", list.option_name())
+            } else {
+                let mentions: Vec<String> = ignored.iter().map(|id| list.mention(*id)).collect();
+                format!("ignored {}s: {}", list.option_name(), mentions.join(", "))
+            };
+            return respond(ctx, slash_command, response).await;
+        }
+        Ok(())
+    }
+}
+
+//get the id of the channel, user or role picked in a slash command
+fn id_option(list: IgnoreList, options: &[CommandDataOption]) -> LogResult<u64> {
+    options
+        .iter()
+        .find(|option| option.name == list.option_name())
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| {
+            LogError::InvalidCommand(format!(
+                "/{} needs the {} option",
+                list.ignore_command(),
+                list.option_name()
+            ))
+        })
+}
@@ -0,0 +1,283 @@
+use crate::command_guild;
+use crate::config::LogCategory;
+use crate::error::LogResult;
+use crate::excerpt;
+use crate::respond;
+use crate::Change;
+use crate::Handler;
+use serenity::builder::CreateEmbed;
+use serenity::model::application::command::Command;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::event::InviteCreateEvent;
+use serenity::model::event::InviteDeleteEvent;
+use serenity::model::id::ChannelId;
+use serenity::model::id::GuildId;
+use serenity::model::invite::RichInvite;
+use serenity::model::permissions::Permissions;
+use serenity::model::user::User;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use std::collections::HashMap;
+
+const LIST_INVITES: &str = "invites";
+
+//maximum length of a slash command response
+const RESPONSE_LIMIT: usize = 2000;
+
+//what is remembered about an invite, to find the invite a member joined with
+#[derive(Clone)]
+pub struct TrackedInvite {
+    pub uses: u64,
+    //0 means unlimited
+    pub max_uses: u64,
+    pub inviter: Option<User>,
+    pub channel_id: ChannelId,
+    //unix timestamp, None for invites that never expire
+    pub expires_at: Option<i64>,
+}
+
+impl From<&RichInvite> for TrackedInvite {
+    fn from(invite: &RichInvite) -> Self {
+        TrackedInvite {
+            uses: invite.uses,
+            max_uses: invite.max_uses,
+            inviter: invite.inviter.clone(),
+            channel_id: invite.channel.id,
+            expires_at: expiry(invite.created_at, invite.max_age),
+        }
+    }
+}
+
+impl From<&InviteCreateEvent> for TrackedInvite {
+    fn from(invite: &InviteCreateEvent) -> Self {
+        TrackedInvite {
+            uses: 0,
+            max_uses: invite.max_uses,
+            inviter: invite.inviter.clone(),
+            channel_id: invite.channel_id,
+            expires_at: expiry(Timestamp::now(), invite.max_age),
+        }
+    }
+}
+
+pub fn is_invites_command(command_name: &str) -> bool {
+    command_name == LIST_INVITES
+}
+
+pub async fn register_commands(ctx: &Context) {
+    let _list_invites = Command::create_global_application_command(ctx, |command| {
+        command.name(LIST_INVITES);
+        command.description("list the invites of this server and how often they were used");
+        command.default_member_permissions(Permissions::MANAGE_GUILD)
+    })
+    .await;
+}
+
+impl Handler {
+    //fetch the invites of a guild, returning the invites known from before
+    pub async fn refresh_invites(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+    ) -> Option<HashMap<String, TrackedInvite>> {
+        //invites can only be fetched with the manage server permission
+        let invites = match g_id.invites(ctx).await {
+            Ok(invites) => invites,
+            Err(why) => {
+                eprintln!("refresh_invites(): unable to fetch the invites of {g_id}: {why}");
+                return None;
+            }
+        };
+        let tracked: HashMap<String, TrackedInvite> = invites
+            .iter()
+            .map(|invite| (invite.code.clone(), TrackedInvite::from(invite)))
+            .collect();
+        self.invites.lock().unwrap().insert(g_id, tracked)
+    }
+
+    //start tracking the invites of a guild the bot wasn't ready for
+    pub async fn load_invites(&self, ctx: &Context, g_id: GuildId) {
+        if !self.invites.lock().unwrap().contains_key(&g_id) {
+            self.refresh_invites(ctx, g_id).await;
+        }
+    }
+
+    //find the invite whose use count went up since the invites were last
+    //fetched
+    pub async fn used_invite(&self, ctx: &Context, g_id: GuildId) -> Option<String> {
+        let previous = self.refresh_invites(ctx, g_id).await?;
+        let invites = self.invites.lock().unwrap();
+        let current = invites.get(&g_id)?;
+
+        //invites created since the last fetch count as having had no uses
+        let mut used = current.iter().filter(|(code, invite)| {
+            invite.uses > previous.get(*code).map_or(0, |previous| previous.uses)
+        });
+        let (code, invite) = used.next()?;
+        //several invites could have been used, so none can be blamed
+        if used.next().is_some() {
+            return None;
+        }
+
+        Some(match &invite.inviter {
+            Some(inviter) => format!("`{code}` created by {}", inviter.tag()),
+            None => format!("`{code}`"),
+        })
+    }
+
+    pub async fn log_invite_create(
+        &self,
+        ctx: &Context,
+        created: InviteCreateEvent,
+    ) -> LogResult<()> {
+        let g_id = match created.guild_id {
+            Some(g_id) => g_id,
+            None => return Ok(()),
+        };
+        let invite = TrackedInvite::from(&created);
+        self.invites
+            .lock()
+            .unwrap()
+            .entry(g_id)
+            .or_default()
+            .insert(created.code.clone(), invite.clone());
+
+        //ignore invites if logging not set up
+        if !self.is_logged(g_id, LogCategory::INVITES) {
+            return Ok(());
+        }
+
+        let mut embed = invite_embed(&created.code, &invite, Change::CREATED);
+        embed
+            .field("max uses:", max_uses(invite.max_uses), true)
+            .field("expires:", expires(invite.expires_at), true);
+        if created.temporary {
+            embed.field(
+                "temporary:",
+                "members are kicked when they go offline",
+                false,
+            );
+        }
+        self.log_event(ctx, g_id, LogCategory::INVITES, embed).await
+    }
+
+    pub async fn log_invite_delete(
+        &self,
+        ctx: &Context,
+        deleted: InviteDeleteEvent,
+    ) -> LogResult<()> {
+        let g_id = match deleted.guild_id {
+            Some(g_id) => g_id,
+            None => return Ok(()),
+        };
+        let invite = self
+            .invites
+            .lock()
+            .unwrap()
+            .get_mut(&g_id)
+            .and_then(|invites| invites.remove(&deleted.code));
+
+        if !self.is_logged(g_id, LogCategory::INVITES) {
+            return Ok(());
+        }
+
+        //invites created before the bot started aren't known
+        let invite = invite.unwrap_or(TrackedInvite {
+            uses: 0,
+            max_uses: 0,
+            inviter: None,
+            channel_id: deleted.channel_id,
+            expires_at: None,
+        });
+        let mut embed = invite_embed(&deleted.code, &invite, Change::DELETED);
+        embed.field(
+            "uses:",
+            format!("{}/{}", invite.uses, max_uses(invite.max_uses)),
+            true,
+        );
+        self.log_event(ctx, g_id, LogCategory::INVITES, embed).await
+    }
+
+    //handle LIST_INVITES
+    pub async fn invites_command(
+        &self,
+        ctx: &Context,
+        slash_command: &ApplicationCommandInteraction,
+    ) -> LogResult<()> {
+        let g_id = command_guild(slash_command)?;
+        //the table is only refreshed at startup and when members join, so
+        //fetch the current use counts
+        self.refresh_invites(ctx, g_id).await;
+        let mut invites: Vec<(String, TrackedInvite)> =
+            match self.invites.lock().unwrap().get(&g_id) {
+                Some(invites) => invites
+                    .iter()
+                    .map(|(code, invite)| (code.clone(), invite.clone()))
+                    .collect(),
+                None => vec![],
+            };
+        //most used first
+        invites.sort_by(|(a_code, a), (b_code, b)| b.uses.cmp(&a.uses).then(a_code.cmp(b_code)));
+
+        let response = if invites.is_empty() {
+            "no invites are being tracked! the bot needs the manage server permission to see invites".to_owned()
+        } else {
+            let lines: Vec<String> = invites
+                .iter()
+                .map(|(code, invite)| {
+                    let inviter = match &invite.inviter {
+                        Some(inviter) => format!(" by <@{}>", inviter.id),
+                        None => String::new(),
+                    };
+                    format!(
+                        "`{code}`: {}/{} uses, <#{}>{inviter}, expires {}",
+                        invite.uses,
+                        max_uses(invite.max_uses),
+                        invite.channel_id,
+                        expires(invite.expires_at)
+                    )
+                })
+                .collect();
+            excerpt(&lines.join("\n"), RESPONSE_LIMIT - 3)
+        };
+        respond(ctx, slash_command, response).await
+    }
+}
+
+fn invite_embed(code: &str, invite: &TrackedInvite, change: Change) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("invite {}", change.name()))
+        .field("invite:", format!("`{code}`"), true)
+        .field("channel:", format!("<#{}>", invite.channel_id), true)
+        .timestamp(Timestamp::now())
+        .color(change.color());
+    if let Some(inviter) = &invite.inviter {
+        embed
+            .field("created by:", format!("<@{}>", inviter.id), true)
+            .author(|author| author.name(inviter.tag()).icon_url(inviter.face()));
+    }
+    embed
+}
+
+//when an invite stops working, max_age 0 means never
+fn expiry(created_at: Timestamp, max_age: u64) -> Option<i64> {
+    match max_age {
+        0 => None,
+        max_age => Some(created_at.unix_timestamp() + max_age as i64),
+    }
+}
+
+fn max_uses(max_uses: u64) -> String {
+    match max_uses {
+        0 => "∞".to_owned(),
+        max_uses => max_uses.to_string(),
+    }
+}
+
+fn expires(expires_at: Option<i64>) -> String {
+    match expires_at {
+        Some(expires_at) => format!("<t:{expires_at}:R>"),
+        None => "never".to_owned(),
+    }
+}
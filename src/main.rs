@@ -1,19 +1,44 @@
-use serde::{Deserialize, Serialize};
-use serde_json::Error;
 use serenity::async_trait;
+use serenity::builder::CreateApplicationCommandOption;
 use serenity::builder::CreateEmbed;
+use serenity::futures::future::join_all;
 use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
 use serenity::model::application::interaction::Interaction;
-use serenity::model::application::interaction::InteractionType;
+use serenity::model::channel::Channel;
+use serenity::model::channel::ChannelCategory;
 use serenity::model::channel::Message;
+use serenity::model::channel::MessageType as DiscordMessageType;
+use serenity::model::channel::PartialGuildChannel;
+use serenity::model::channel::Reaction;
+use serenity::model::event::ChannelPinsUpdateEvent;
+use serenity::model::event::GuildMemberUpdateEvent;
+use serenity::model::event::GuildMembersChunkEvent;
+use serenity::model::event::InviteCreateEvent;
+use serenity::model::event::InviteDeleteEvent;
 use serenity::model::gateway::Activity;
-use serenity::model::guild::UnavailableGuild;
+use serenity::model::guild::Emoji;
+use serenity::model::guild::Guild;
+use serenity::model::guild::Member;
+use serenity::model::guild::PartialGuild;
+use serenity::model::guild::Role;
+use serenity::model::id::ChannelId;
+use serenity::model::id::EmojiId;
+use serenity::model::id::GuildId;
+use serenity::model::id::MessageId;
+use serenity::model::id::RoleId;
+use serenity::model::id::StickerId;
+use serenity::model::id::UserId;
 use serenity::model::permissions::Permissions;
 use serenity::model::prelude::Attachment;
 use serenity::model::prelude::AttachmentType;
 use serenity::model::prelude::GuildChannel;
 use serenity::model::prelude::MessageUpdateEvent;
 use serenity::model::prelude::Ready;
+use serenity::model::sticker::Sticker;
+use serenity::model::user::User;
+use serenity::model::voice::VoiceState;
 use serenity::model::Timestamp;
 use serenity::prelude::*;
 use serenity::utils::Color;
@@ -21,34 +46,112 @@ use serenity::Client;
 use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs;
 use std::hash::Hash;
 use std::hash::Hasher;
-use std::path::Path;
 use std::sync::Mutex;
 
-const DISCORD_AUTH_PATH: &'static str = "discord.auth";
-const JSON_PATH: &'static str = "./servers.json";
-//Clueless just assume tag won't change
-const LOGGER_TAG: &'static str = "MessageLogger#0584";
-
-const INIT_LOG: &'static str = "setuplogging";
-const END_LOG: &'static str = "removelogging";
-
-/*TODO:
-    properly log replies to other messages
-    maybe also try splitting messages that are longer than field limit
-*/
-
+mod archive;
+mod audit_log;
+mod channels;
+mod config;
+mod database;
+mod diff;
+mod error;
+#[cfg(test)]
+mod fixtures;
+mod ignore;
+mod invites;
+mod media;
+mod member_cache;
+mod members;
+mod message_cache;
+mod moderation;
+mod pins;
+mod reactions;
+mod roles;
+mod server;
+mod threads;
+mod toggles;
+mod uploads;
+mod voice;
+use archive::ArchivedAttachment;
+use channels::ChannelSnapshot;
+use config::GuildConfig;
+use config::LogCategory;
+use database::Database;
+use error::LogError;
+use error::LogResult;
+use invites::TrackedInvite;
+use media::AttachmentKind;
+use member_cache::MemberCache;
+use message_cache::CachedMessage;
+use message_cache::MessageCache;
+use moderation::ModAction;
+use reactions::ReactionKey;
+use server::ServerSnapshot;
+use uploads::file_size;
+use uploads::Upload;
+use voice::format_duration;
+use voice::VoiceSession;
+
+const DISCORD_AUTH_PATH: &str = "discord.auth";
+const DATABASE_PATH: &str = "./logger.db";
+//servers.json from before the database, imported on first start
+const JSON_PATH: &str = "./servers.json";
+const IMPORTED_JSON_PATH: &str = "./servers.json.imported";
+const INIT_LOG: &str = "setuplogging";
+const END_LOG: &str = "removelogging";
+//option of INIT_LOG and END_LOG that picks which events are routed
+const CATEGORY_OPTION: &str = "category";
+const ALL_CATEGORIES: &str = "all";
+
+//how many recently sent messages are remembered for logging deletions
+const MESSAGE_CACHE_SIZE: usize = 10000;
+//maximum length of an embed's title and of a field's value
+const TITLE_LIMIT: usize = 256;
+const FIELD_LIMIT: usize = 1024;
+//maximum number of fields and characters in a single embed
+const EMBED_FIELD_LIMIT: usize = 25;
+const EMBED_LIMIT: usize = 6000;
+//how much of a replied to message is quoted in the log
+const REPLY_EXCERPT_LENGTH: usize = 100;
+
+#[allow(clippy::upper_case_acronyms)]
 enum MessageType {
     POSTED,
     EDITED,
+    DELETED,
+    PINNED,
+    UNPINNED,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct SaveMap {
-    #[serde(flatten)]
-    map: HashMap<String, u64>,
+//the kind of change made to a channel, role or other part of a guild
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
+enum Change {
+    CREATED,
+    UPDATED,
+    DELETED,
+}
+
+impl Change {
+    fn name(&self) -> &'static str {
+        match self {
+            Change::CREATED => "created",
+            Change::UPDATED => "updated",
+            Change::DELETED => "deleted",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Change::CREATED => Color::DARK_GREEN,
+            Change::UPDATED => Color::BLUE,
+            Change::DELETED => Color::DARK_RED,
+        }
+    }
 }
 
 struct LogInfo {
@@ -56,26 +159,215 @@ struct LogInfo {
     msg_link: String,
     channel_name: String,
     message_content: String,
+    previous_content: Option<String>,
     message_type: MessageType,
+    reply: Option<ReplyInfo>,
+    //who pinned or unpinned the message
+    moderator: Option<String>,
     time_sent: Timestamp,
     color: Color,
     author_name: String,
     author_face: String,
     attachments: Option<Vec<Attachment>>,
+    //local copies of the attachments, uploaded instead of linking to discord
+    archived: Vec<ArchivedAttachment>,
+    //largest file the log channel's guild allows
+    upload_limit: u64,
+    //whether the message was flagged as a voice message
+    voice_message: bool,
+    ctx: Context,
+}
+
+//the message that a logged message replied to
+struct ReplyInfo {
+    author_name: String,
+    excerpt: String,
+    msg_link: String,
+    pinged: bool,
+}
+
+struct PurgeInfo {
+    log_channel: GuildChannel,
+    channel_link: String,
+    channel_name: String,
+    deleted_count: usize,
+    report: String,
     ctx: Context,
 }
 
 struct Handler {
-    map: Mutex<HashMap<String, u64>>,
+    map: Mutex<HashMap<String, GuildConfig>>,
+    database: Mutex<Database>,
+    cache: Mutex<MessageCache>,
+    members: Mutex<MemberCache>,
+    channels: Mutex<HashMap<ChannelId, ChannelSnapshot>>,
+    roles: Mutex<HashMap<RoleId, Role>>,
+    servers: Mutex<HashMap<GuildId, ServerSnapshot>>,
+    //names of each guild's emojis and stickers
+    emojis: Mutex<HashMap<GuildId, HashMap<EmojiId, String>>>,
+    stickers: Mutex<HashMap<GuildId, HashMap<StickerId, String>>>,
+    voice: Mutex<HashMap<(GuildId, UserId), VoiceSession>>,
+    //reactions held back in case they are quickly removed, with when they were added
+    reactions: Mutex<HashMap<ReactionKey, Timestamp>>,
+    //invites of each guild by code, to find the invite a member joined with
+    invites: Mutex<HashMap<GuildId, HashMap<String, TrackedInvite>>>,
+    //MessageLogger's own user id, known once the bot is ready
+    bot_id: Mutex<Option<UserId>>,
+    //broken log channels that guilds have already been warned about
+    warned: Mutex<HashSet<(GuildId, ChannelId)>>,
+    //when expired attachments were last deleted from the archive
+    archive_pruned: Mutex<Option<i64>>,
 }
 
 impl Handler {
     fn new() -> Self {
-        let map: HashMap<String, u64> = read_json().unwrap();
+        let database = Database::open(DATABASE_PATH, JSON_PATH, IMPORTED_JSON_PATH)
+            .expect("Handler::new(): unable to open the database!");
+        let map: HashMap<String, GuildConfig> = database
+            .load_configs()
+            .expect("Handler::new(): unable to load the guild configs!");
 
         Handler {
             map: Mutex::new(map),
+            database: Mutex::new(database),
+            cache: Mutex::new(MessageCache::new(MESSAGE_CACHE_SIZE)),
+            members: Mutex::new(MemberCache::default()),
+            channels: Mutex::new(HashMap::new()),
+            roles: Mutex::new(HashMap::new()),
+            servers: Mutex::new(HashMap::new()),
+            emojis: Mutex::new(HashMap::new()),
+            stickers: Mutex::new(HashMap::new()),
+            voice: Mutex::new(HashMap::new()),
+            reactions: Mutex::new(HashMap::new()),
+            invites: Mutex::new(HashMap::new()),
+            bot_id: Mutex::new(None),
+            warned: Mutex::new(HashSet::new()),
+            archive_pruned: Mutex::new(None),
+        }
+    }
+
+    //write a guild's config to the database, forgetting it once it is empty
+    fn save_config(&self, g_id: GuildId) -> LogResult<()> {
+        let g_id_str = g_id.to_string();
+        let mut map = self.map.lock().unwrap();
+        let mut database = self.database.lock().unwrap();
+        match map.get(&g_id_str) {
+            Some(config) if !config.is_empty() => database.save_config(g_id.0, config)?,
+            _ => {
+                map.remove(&g_id_str);
+                database.delete_guild(g_id.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    //record an error in the process log and warn the guild about problems
+    //with its log channel, once per log channel
+    async fn report_error(
+        &self,
+        ctx: &Context,
+        guild_id: Option<GuildId>,
+        event: &str,
+        why: LogError,
+    ) {
+        eprintln!("{event}(): {why}");
+
+        let (g_id, c_id, warning) = match (guild_id, why.log_channel(), why.guild_warning()) {
+            (Some(g_id), Some(c_id), Some(warning)) => (g_id, c_id, warning),
+            _ => return,
+        };
+        if !self.warned.lock().unwrap().insert((g_id, c_id)) {
+            return;
+        }
+
+        if let Err(why) = warn_guild(ctx, g_id, &warning).await {
+            eprintln!("{event}(): unable to warn guild {g_id}: {why}");
+        }
+    }
+
+    //send an embed to the channel a guild logs this category of events in
+    async fn log_event(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        category: LogCategory,
+        embed: CreateEmbed,
+    ) -> LogResult<()> {
+        let c_id = match self.log_channel_id(&g_id.to_string(), category) {
+            Some(c_id) => c_id,
+            None => return Ok(()),
+        };
+        let log_channel = fetch_log_channel(ctx, c_id).await?;
+        log_channel
+            .send_message(ctx, |r| r.set_embed(embed))
+            .await
+            .map_err(|why| LogError::from_log_channel(why, log_channel.id))?;
+        Ok(())
+    }
+
+    //whether a user is MessageLogger itself
+    fn is_self(&self, user_id: UserId) -> bool {
+        *self.bot_id.lock().unwrap() == Some(user_id)
+    }
+
+    //whether a guild logs this category of events anywhere
+    fn is_logged(&self, g_id: GuildId, category: LogCategory) -> bool {
+        self.log_channel_id(&g_id.to_string(), category).is_some()
+    }
+
+    //get the channel that a guild logs this category of events in
+    fn log_channel_id(&self, g_id_str: &String, category: LogCategory) -> Option<u64> {
+        self.map
+            .lock()
+            .unwrap()
+            .get(g_id_str)
+            .and_then(|config| config.channel_for(category))
+    }
+
+    //find the message that msg is replying to
+    async fn reply_info(&self, ctx: &Context, msg: &Message) -> Option<ReplyInfo> {
+        if msg.kind != DiscordMessageType::InlineReply {
+            return None;
+        }
+        let reference = msg.message_reference.as_ref()?;
+        let parent_id = reference.message_id?;
+        let parent_link = parent_id.link(reference.channel_id, reference.guild_id);
+        let pinged = |author_id: UserId| msg.mentions.iter().any(|user| user.id == author_id);
+
+        //discord usually sends the parent along with the reply
+        let parent = match &msg.referenced_message {
+            Some(parent) => Some(*parent.clone()),
+            None => ctx
+                .http
+                .get_message(reference.channel_id.0, parent_id.0)
+                .await
+                .ok(),
+        };
+        if let Some(parent) = parent {
+            return Some(ReplyInfo {
+                author_name: parent.author.name.clone(),
+                excerpt: excerpt(&parent.content, REPLY_EXCERPT_LENGTH),
+                msg_link: parent_link,
+                pinged: pinged(parent.author.id),
+            });
         }
+
+        //the parent might have been deleted but still be cached
+        let cache = self.cache.lock().unwrap();
+        Some(match cache.get(&parent_id) {
+            Some(cached) => ReplyInfo {
+                author_name: cached.author_name.clone(),
+                excerpt: excerpt(&cached.content, REPLY_EXCERPT_LENGTH),
+                msg_link: parent_link,
+                pinged: pinged(cached.author_id),
+            },
+            None => ReplyInfo {
+                author_name: "unknown".to_owned(),
+                excerpt: "*the original message was deleted*".to_owned(),
+                msg_link: parent_link,
+                pinged: false,
+            },
+        })
     }
 }
 
@@ -83,16 +375,42 @@ impl Handler {
 impl EventHandler for Handler {
     //when MessageLogger starts
     async fn ready(&self, ctx: Context, data_about_bot: Ready) {
-        println!("{LOGGER_TAG} is ready");
+        println!("{} is ready", data_about_bot.user.name);
+        *self.bot_id.lock().unwrap() = Some(data_about_bot.user.id);
 
         let activity = Activity::playing("/".to_owned() + INIT_LOG);
         ctx.set_activity(activity).await;
 
-        sync_json(data_about_bot.guilds).unwrap();
+        //forget guilds that MessageLogger was removed from while offline
+        let guild_ids: Vec<String> = data_about_bot
+            .guilds
+            .iter()
+            .map(|guild| guild.id.to_string())
+            .collect();
+        let stale: Vec<String> = self
+            .map
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|g_id_str| !guild_ids.contains(g_id_str))
+            .cloned()
+            .collect();
+        for g_id_str in stale {
+            self.map.lock().unwrap().remove(&g_id_str);
+            if let Ok(g_id) = g_id_str.parse::<u64>() {
+                if let Err(why) = self.save_config(GuildId(g_id)) {
+                    eprintln!("ready(): unable to forget guild {g_id}: {why}");
+                }
+            }
+        }
 
         let _init_log = Command::create_global_application_command(&ctx, |command| {
             command.name(INIT_LOG);
             command.description("setup logging for this channel");
+            command.create_option(|option| {
+                add_category_choices(option);
+                option.description("only log these events in this channel")
+            });
             command.default_member_permissions(Permissions::MANAGE_GUILD)
         })
         .await;
@@ -100,294 +418,868 @@ impl EventHandler for Handler {
         let _end_log = Command::create_global_application_command(&ctx, |command| {
             command.name(END_LOG);
             command.description("remove logging for your server");
+            command.create_option(|option| {
+                add_category_choices(option);
+                option.description("only stop logging these events in their own channel")
+            });
             command.default_member_permissions(Permissions::MANAGE_GUILD)
         })
         .await;
+
+        ignore::register_commands(&ctx).await;
+        toggles::register_commands(&ctx).await;
+        invites::register_commands(&ctx).await;
+
+        //start counting invite uses before anyone joins, every guild at once
+        //so the bot isn't held up by one request per guild
+        let refreshes = data_about_bot
+            .guilds
+            .iter()
+            .map(|guild| self.refresh_invites(&ctx, guild.id));
+        join_all(refreshes).await;
     }
 
     //handle interactions
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         //handle slash commands
-        if interaction.kind() == InteractionType::ApplicationCommand {
-            let slash_command = interaction.application_command().expect(
-                "interaction_create(): unable to convert the interaction 
-                to an application command!",
-            );
+        if let Interaction::ApplicationCommand(slash_command) = interaction {
+            if let Err(why) = self.run_command(&ctx, &slash_command).await {
+                eprintln!(
+                    "interaction_create(): /{} failed: {why}",
+                    slash_command.data.name
+                );
+                //the command might have failed before responding
+                let _ = respond(&ctx, &slash_command, format!("something went wrong: {why}")).await;
+            }
+        }
+    }
 
-            let command_name = slash_command.data.name.clone();
-
-            if command_name == INIT_LOG {
-                //get current channel id
-                let c_id = *slash_command.channel_id.as_u64();
-                let g_id_str = slash_command
-                    .guild_id
-                    .expect((INIT_LOG.to_owned() + ": unable to get the guild_id!").as_str())
-                    .to_string();
-
-                //update the save_map with the new server,channel pair
-                self.map.lock().unwrap().insert(g_id_str, c_id);
-                //update the json file
-                write_json(&*self.map.lock().unwrap())
-                    .expect((INIT_LOG.to_owned() + ": unable to write to json file!").as_str());
-
-                slash_command
-                    .create_interaction_response(&ctx, |reply| {
-                        reply.interaction_response_data(|message| {
-                            message
-                                .content("logging has been successfully set up for this channel!")
-                        })
-                    })
-                    .await
-                    .unwrap();
+    //when a user sends a message
+    async fn message(&self, ctx: Context, msg: Message) {
+        let guild_id = msg.guild_id;
+        if let Err(why) = self.log_posted(&ctx, msg).await {
+            self.report_error(&ctx, guild_id, "message", why).await;
+        }
+    }
 
-                return;
-            }
+    //when a message is updated
+    async fn message_update(&self, ctx: Context, updated: MessageUpdateEvent) {
+        let guild_id = updated.guild_id;
+        if let Err(why) = self.log_edited(&ctx, updated).await {
+            self.report_error(&ctx, guild_id, "message_update", why)
+                .await;
+        }
+    }
 
-            if command_name == END_LOG {
-                let g_id_str: String = slash_command
-                    .guild_id
-                    .expect((END_LOG.to_owned() + ": unable to get the guild_id!").as_str())
-                    .to_string();
+    //when a message is deleted
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        _channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Err(why) = self.log_deleted(&ctx, deleted_message_id, guild_id).await {
+            self.report_error(&ctx, guild_id, "message_delete", why)
+                .await;
+        }
+    }
 
-                //remove from map and update json
-                {
-                    let _map = self.map.lock().unwrap().remove(&g_id_str);
-                }
-                match delete_entry(&g_id_str) {
-                    //send success message
-                    Some(_id) => {
-                        slash_command
-                            .create_interaction_response(&ctx, |reply| {
-                                reply.interaction_response_data(|message| {
-                                    message.content("logging successfully stopped for this server!")
-                                })
-                            })
-                            .await
-                            .unwrap();
-                    }
-                    //send error message
-                    None => {
-                        slash_command
-                            .create_interaction_response(&ctx, |reply| {
-                                reply.interaction_response_data(|message| {
-                                    message
-                                        .content("logging has not been set up yet for your server!")
-                                })
-                            })
-                            .await
-                            .unwrap();
+    //when a guild becomes available
+    async fn guild_create(&self, ctx: Context, guild: Guild) {
+        self.load_channels(&guild);
+        self.load_roles(&guild);
+        self.load_server(&guild);
+        self.load_voice_states(&guild);
+        self.load_members(&ctx, &guild);
+        self.load_invites(&ctx, guild.id).await;
+    }
+
+    //when a member joins, leaves or moves between voice channels, or is
+    //muted or deafened
+    async fn voice_state_update(&self, ctx: Context, new: VoiceState) {
+        let guild_id = new.guild_id;
+        if let Err(why) = self.log_voice_state(&ctx, new).await {
+            self.report_error(&ctx, guild_id, "voice_state_update", why)
+                .await;
+        }
+    }
+
+    //when a reaction is added to a message
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        let guild_id = add_reaction.guild_id;
+        if let Err(why) = self.log_reaction_add(&ctx, add_reaction).await {
+            self.report_error(&ctx, guild_id, "reaction_add", why).await;
+        }
+    }
+
+    //when a reaction is removed from a message
+    async fn reaction_remove(&self, ctx: Context, removed_reaction: Reaction) {
+        let guild_id = removed_reaction.guild_id;
+        if let Err(why) = self.log_reaction_remove(&ctx, removed_reaction).await {
+            self.report_error(&ctx, guild_id, "reaction_remove", why)
+                .await;
+        }
+    }
+
+    //when every reaction is removed from a message
+    async fn reaction_remove_all(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        removed_from_message_id: MessageId,
+    ) {
+        if let Err(why) = self
+            .log_reaction_remove_all(&ctx, channel_id, removed_from_message_id)
+            .await
+        {
+            let guild_id = self
+                .channels
+                .lock()
+                .unwrap()
+                .get(&channel_id)
+                .map(|channel| channel.guild_id);
+            self.report_error(&ctx, guild_id, "reaction_remove_all", why)
+                .await;
+        }
+    }
+
+    //when a message is pinned or unpinned
+    async fn channel_pins_update(&self, ctx: Context, pin: ChannelPinsUpdateEvent) {
+        let guild_id = pin.guild_id;
+        if let Err(why) = self.log_pins_update(&ctx, pin).await {
+            self.report_error(&ctx, guild_id, "channel_pins_update", why)
+                .await;
+        }
+    }
+
+    //when a guild's settings change
+    async fn guild_update(&self, ctx: Context, new_but_incomplete_data: PartialGuild) {
+        let guild_id = new_but_incomplete_data.id;
+        if let Err(why) = self.log_server_update(&ctx, new_but_incomplete_data).await {
+            self.report_error(&ctx, Some(guild_id), "guild_update", why)
+                .await;
+        }
+    }
+
+    //when emojis are added, removed or renamed
+    async fn guild_emojis_update(
+        &self,
+        ctx: Context,
+        guild_id: GuildId,
+        current_state: HashMap<EmojiId, Emoji>,
+    ) {
+        if let Err(why) = self.log_emojis_update(&ctx, guild_id, current_state).await {
+            self.report_error(&ctx, Some(guild_id), "guild_emojis_update", why)
+                .await;
+        }
+    }
+
+    //when stickers are added, removed or renamed
+    async fn guild_stickers_update(
+        &self,
+        ctx: Context,
+        guild_id: GuildId,
+        current_state: HashMap<StickerId, Sticker>,
+    ) {
+        if let Err(why) = self
+            .log_stickers_update(&ctx, guild_id, current_state)
+            .await
+        {
+            self.report_error(&ctx, Some(guild_id), "guild_stickers_update", why)
+                .await;
+        }
+    }
+
+    //when an invite is created
+    async fn invite_create(&self, ctx: Context, data: InviteCreateEvent) {
+        let guild_id = data.guild_id;
+        if let Err(why) = self.log_invite_create(&ctx, data).await {
+            self.report_error(&ctx, guild_id, "invite_create", why)
+                .await;
+        }
+    }
+
+    //when an invite is deleted or expires
+    async fn invite_delete(&self, ctx: Context, data: InviteDeleteEvent) {
+        let guild_id = data.guild_id;
+        if let Err(why) = self.log_invite_delete(&ctx, data).await {
+            self.report_error(&ctx, guild_id, "invite_delete", why)
+                .await;
+        }
+    }
+
+    //when a role is created
+    async fn guild_role_create(&self, ctx: Context, new: Role) {
+        let guild_id = new.guild_id;
+        if let Err(why) = self.log_role_create(&ctx, new).await {
+            self.report_error(&ctx, Some(guild_id), "guild_role_create", why)
+                .await;
+        }
+    }
+
+    //when a role's name, permissions, color or position change
+    async fn guild_role_update(&self, ctx: Context, new_data: Role) {
+        let guild_id = new_data.guild_id;
+        if let Err(why) = self.log_role_update(&ctx, new_data).await {
+            self.report_error(&ctx, Some(guild_id), "guild_role_update", why)
+                .await;
+        }
+    }
+
+    //when a role is deleted
+    async fn guild_role_delete(&self, ctx: Context, guild_id: GuildId, removed_role_id: RoleId) {
+        if let Err(why) = self.log_role_delete(&ctx, guild_id, removed_role_id).await {
+            self.report_error(&ctx, Some(guild_id), "guild_role_delete", why)
+                .await;
+        }
+    }
+
+    //when a channel is created
+    async fn channel_create(&self, ctx: Context, channel: &GuildChannel) {
+        let created = ChannelSnapshot::from(channel);
+        if let Err(why) = self.log_channel_create(&ctx, created).await {
+            self.report_error(&ctx, Some(channel.guild_id), "channel_create", why)
+                .await;
+        }
+    }
+
+    //when a category is created
+    async fn category_create(&self, ctx: Context, category: &ChannelCategory) {
+        let created = ChannelSnapshot::from(category);
+        if let Err(why) = self.log_channel_create(&ctx, created).await {
+            self.report_error(&ctx, Some(category.guild_id), "category_create", why)
+                .await;
+        }
+    }
+
+    //when a channel or category is changed
+    async fn channel_update(&self, ctx: Context, new_data: Channel) {
+        let updated = match ChannelSnapshot::from_channel(&new_data) {
+            Some(updated) => updated,
+            None => return,
+        };
+        let guild_id = updated.guild_id;
+        if let Err(why) = self.log_channel_update(&ctx, updated).await {
+            self.report_error(&ctx, Some(guild_id), "channel_update", why)
+                .await;
+        }
+    }
+
+    //when a channel is deleted
+    async fn channel_delete(&self, ctx: Context, channel: &GuildChannel) {
+        let deleted = ChannelSnapshot::from(channel);
+        if let Err(why) = self.log_channel_delete(&ctx, deleted).await {
+            self.report_error(&ctx, Some(channel.guild_id), "channel_delete", why)
+                .await;
+        }
+    }
+
+    //when a category is deleted
+    async fn category_delete(&self, ctx: Context, category: &ChannelCategory) {
+        let deleted = ChannelSnapshot::from(category);
+        if let Err(why) = self.log_channel_delete(&ctx, deleted).await {
+            self.report_error(&ctx, Some(category.guild_id), "category_delete", why)
+                .await;
+        }
+    }
+
+    //when a thread is created
+    async fn thread_create(&self, ctx: Context, thread: GuildChannel) {
+        let created = ChannelSnapshot::from(&thread);
+        if let Err(why) = self.log_channel_create(&ctx, created).await {
+            self.report_error(&ctx, Some(thread.guild_id), "thread_create", why)
+                .await;
+        }
+    }
+
+    //when a thread is renamed, archived or locked
+    async fn thread_update(&self, ctx: Context, thread: GuildChannel) {
+        let updated = ChannelSnapshot::from(&thread);
+        if let Err(why) = self.log_channel_update(&ctx, updated).await {
+            self.report_error(&ctx, Some(thread.guild_id), "thread_update", why)
+                .await;
+        }
+    }
+
+    //when a thread is deleted
+    async fn thread_delete(&self, ctx: Context, thread: PartialGuildChannel) {
+        let guild_id = thread.guild_id;
+        if let Err(why) = self.log_thread_delete(&ctx, thread).await {
+            self.report_error(&ctx, Some(guild_id), "thread_delete", why)
+                .await;
+        }
+    }
+
+    //when members of a large guild arrive after requesting them
+    async fn guild_members_chunk(&self, _ctx: Context, chunk: GuildMembersChunkEvent) {
+        self.load_member_chunk(&chunk);
+    }
+
+    //when a member joins a guild
+    async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
+        let guild_id = new_member.guild_id;
+        if let Err(why) = self.log_member_join(&ctx, new_member).await {
+            self.report_error(&ctx, Some(guild_id), "guild_member_addition", why)
+                .await;
+        }
+    }
+
+    //when a member leaves, is kicked or is banned from a guild
+    async fn guild_member_removal(&self, ctx: Context, guild_id: GuildId, kicked: User) {
+        if let Err(why) = self.log_member_leave(&ctx, guild_id, kicked).await {
+            self.report_error(&ctx, Some(guild_id), "guild_member_removal", why)
+                .await;
+        }
+    }
+
+    //when a member's roles, nickname, avatar or timeout change
+    async fn guild_member_update(&self, ctx: Context, new: GuildMemberUpdateEvent) {
+        let previous = self.update_member(&new);
+        if let Err(why) = self.log_timeout(&ctx, &new, previous.as_ref()).await {
+            self.report_error(&ctx, Some(new.guild_id), "guild_member_update", why)
+                .await;
+        }
+        if let Err(why) = self.log_member_update(&ctx, &new, previous.as_ref()).await {
+            self.report_error(&ctx, Some(new.guild_id), "guild_member_update", why)
+                .await;
+        }
+    }
+
+    //when a user is banned from a guild
+    async fn guild_ban_addition(&self, ctx: Context, guild_id: GuildId, banned_user: User) {
+        if let Err(why) = self
+            .log_mod_action(&ctx, guild_id, &banned_user, ModAction::BAN, None)
+            .await
+        {
+            self.report_error(&ctx, Some(guild_id), "guild_ban_addition", why)
+                .await;
+        }
+    }
+
+    //when a user's ban is lifted
+    async fn guild_ban_removal(&self, ctx: Context, guild_id: GuildId, unbanned_user: User) {
+        if let Err(why) = self
+            .log_mod_action(&ctx, guild_id, &unbanned_user, ModAction::UNBAN, None)
+            .await
+        {
+            self.report_error(&ctx, Some(guild_id), "guild_ban_removal", why)
+                .await;
+        }
+    }
+
+    //when several messages are deleted at once (purges)
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Err(why) = self
+            .log_purged(&ctx, channel_id, multiple_deleted_messages_ids, guild_id)
+            .await
+        {
+            self.report_error(&ctx, guild_id, "message_delete_bulk", why)
+                .await;
+        }
+    }
+}
+
+impl Handler {
+    //handle INIT_LOG, END_LOG and the ignore list commands
+    async fn run_command(
+        &self,
+        ctx: &Context,
+        slash_command: &ApplicationCommandInteraction,
+    ) -> LogResult<()> {
+        let command_name = slash_command.data.name.clone();
+
+        if command_name == INIT_LOG {
+            //get current channel id
+            let c_id = *slash_command.channel_id.as_u64();
+            let g_id = command_guild(slash_command)?;
+            let g_id_str = g_id.to_string();
+
+            let category = category_option(slash_command);
+
+            //update the save_map with the new server,channel pair
+            {
+                let mut map = self.map.lock().unwrap();
+                let config = map.entry(g_id_str).or_default();
+                match category {
+                    Some(category) => {
+                        config.routes.insert(category, c_id);
                     }
+                    None => config.channel = Some(c_id),
                 }
-                return;
             }
+            //update the database
+            self.save_config(g_id)?;
+            //warn again if the new log channel breaks too
+            self.warned
+                .lock()
+                .unwrap()
+                .retain(|(warned_g_id, _)| *warned_g_id != g_id);
+
+            let response = match category {
+                Some(category) => format!(
+                    "{} will now be logged in this channel!",
+                    category.description()
+                ),
+                None => "logging has been successfully set up for this channel!".to_owned(),
+            };
+            return respond(ctx, slash_command, response).await;
         }
+
+        if command_name == END_LOG {
+            let g_id = command_guild(slash_command)?;
+            let g_id_str = g_id.to_string();
+
+            //only remove the channel of one category
+            if let Some(category) = category_option(slash_command) {
+                let removed = match self.map.lock().unwrap().get_mut(&g_id_str) {
+                    Some(config) => config.routes.remove(&category),
+                    None => None,
+                };
+                self.save_config(g_id)?;
+
+                let response = match removed {
+                    Some(_id) => format!(
+                        "{} no longer have their own log channel!",
+                        category.description()
+                    ),
+                    None => format!(
+                        "{} do not have their own log channel!",
+                        category.description()
+                    ),
+                };
+                return respond(ctx, slash_command, response).await;
+            }
+
+            //only forget the log channels, ignore lists and toggles are kept
+            //for when logging is set up again
+            let removed = match self.map.lock().unwrap().get_mut(&g_id_str) {
+                Some(config) => {
+                    let had_channel = config.channel.take().is_some();
+                    let had_routes = !config.routes.is_empty();
+                    config.routes.clear();
+                    had_channel || had_routes
+                }
+                None => false,
+            };
+            self.save_config(g_id)?;
+            let response = if removed {
+                //send success message
+                "logging successfully stopped for this server!"
+            } else {
+                //send error message
+                "logging has not been set up yet for your server!"
+            };
+            return respond(ctx, slash_command, response.to_owned()).await;
+        }
+
+        if ignore::is_ignore_command(&command_name) {
+            return self.ignore_command(ctx, slash_command).await;
+        }
+
+        if invites::is_invites_command(&command_name) {
+            return self.invites_command(ctx, slash_command).await;
+        }
+
+        if toggles::is_toggle_command(&command_name) {
+            return self.toggle_command(ctx, slash_command).await;
+        }
+        Ok(())
     }
 
-    //when a user sends a message
-    async fn message(&self, ctx: Context, msg: Message) {
+    async fn log_posted(&self, ctx: &Context, msg: Message) -> LogResult<()> {
         let author = msg.author.clone();
 
         //ignore messages from MessageLogger
-        if author.bot && author.tag().as_str() == LOGGER_TAG {
-            return;
+        if self.is_self(author.id) {
+            return Ok(());
         }
 
-        let g_id = msg
-            .guild_id
-            .expect("message(): unable to get the guild_id!");
+        //messages in DMs are never logged
+        let g_id = match msg.guild_id {
+            Some(g_id) => g_id,
+            None => return Ok(()),
+        };
         let g_id_str = g_id.to_string();
 
-        //ignore messages if logging not set up
-        if {
-            let m = self.map.lock().unwrap();
-            !m.contains_key(&g_id_str)
-        } {
-            return;
+        //ignore messages if logging not set up, messages are still cached
+        //when only edits are logged so that deletions can be logged
+        let post_channel = self.log_channel_id(&g_id_str, LogCategory::MESSAGES);
+        let edit_channel = self.log_channel_id(&g_id_str, LogCategory::EDITS);
+        if post_channel.is_none() && edit_channel.is_none() {
+            return Ok(());
         }
 
-        //get the channel id associated with the guild id
-        let c_id = {
-            *self
-                .map
-                .lock()
-                .unwrap()
-                .get(&g_id_str)
-                .expect("message(): unable to get the channel id from the hash map!")
+        //ignore messages from ignored users, roles, bots and webhooks
+        let roles = match &msg.member {
+            Some(member) => member.roles.clone(),
+            None => vec![],
         };
-
-        //turn the c_id into a guild channel
-        let log_channel = ctx
-            .http
-            .get_channel(c_id)
-            .await
-            .expect("message(): unable to get the channel!")
-            .guild()
-            .expect("message(): unable to get the guild channel!");
+        if self.is_ignored_author(&g_id_str, &author, msg.webhook_id.is_some(), &roles) {
+            return Ok(());
+        }
 
         //get the channel name to format it as: `#channel_name` in the embed
-        let guild_channel = msg
-            .channel(&ctx)
-            .await
-            .expect("message(): unable to get the channel from the message!")
-            .guild()
-            .expect("message(): unable to get the guild from the channel!");
-        let channel_name = "#".to_owned() + guild_channel.name();
+        let guild_channel = match msg.channel(ctx).await?.guild() {
+            Some(guild_channel) => guild_channel,
+            None => return Ok(()),
+        };
+        if !self.is_logged_channel(&guild_channel)
+            || self
+                .is_ignored_channel(ctx, &g_id_str, &guild_channel)
+                .await
+        {
+            return Ok(());
+        }
+        let channel_name = self.channel_title(ctx, &guild_channel).await;
         let time = msg.timestamp;
         let display_color = color_hash(&channel_name, &author.tag(), time);
 
-        let nickname = match author.nick_in(&ctx, g_id).await {
-            Some(nick) => nick,
-            None => author.name.clone(),
-        };
-
-        let face = match g_id.member(&ctx, author.id).await {
-            Ok(m) => match m.avatar_url() {
-                Some(url) => url,
-                None => author.face(),
+        let member = g_id.member(ctx, author.id).await.ok();
+        let nickname = member_name(&author, member.as_ref().and_then(|m| m.nick.as_ref()));
+        let face = member_face(
+            g_id,
+            &author,
+            member.as_ref().and_then(|m| m.avatar.as_ref()),
+        );
+
+        let reply = self.reply_info(ctx, &msg).await;
+
+        let voice_message =
+            media::is_voice_message(ctx, msg.channel_id, msg.id, &msg.attachments).await;
+
+        //save the attachments before discord's links expire
+        self.archive_attachments(ctx, g_id, msg.id, msg.channel_id, &msg.attachments)
+            .await;
+
+        //remember the message in case it gets deleted later
+        self.cache.lock().unwrap().insert(
+            msg.id,
+            CachedMessage {
+                msg_link: msg.link(),
+                channel_name: channel_name.clone(),
+                content: msg.content.clone(),
+                color: display_color,
+                author_id: author.id,
+                author_name: nickname.clone(),
+                author_face: face.clone(),
+                attachments: msg.attachments.clone(),
+                voice_message,
             },
-            Err(_) => author.face(),
+        );
+
+        //get the channel id for posted messages
+        let c_id = match post_channel {
+            Some(c_id) => c_id,
+            None => return Ok(()),
         };
 
+        //turn the c_id into a guild channel
+        let log_channel = fetch_log_channel(ctx, c_id).await?;
+        let upload_limit = self.upload_limit(ctx, log_channel.guild_id).await;
+
         let send_info = LogInfo {
-            log_channel: log_channel,
+            log_channel,
             msg_link: msg.link(),
-            channel_name: channel_name,
+            channel_name,
             message_content: msg.content,
+            previous_content: None,
             message_type: MessageType::POSTED,
+            reply,
+            moderator: None,
             time_sent: time,
             color: Color::new(display_color),
             author_name: nickname,
             author_face: face,
             attachments: Some(msg.attachments),
-            ctx: ctx,
+            archived: self.archived_attachments(msg.id),
+            upload_limit,
+            voice_message,
+            ctx: ctx.clone(),
         };
 
-        log_message(send_info).await;
+        log_message(send_info).await
     }
 
-    //when a message is updated
-    async fn message_update(&self, ctx: Context, updated: MessageUpdateEvent) {
+    async fn log_edited(&self, ctx: &Context, updated: MessageUpdateEvent) -> LogResult<()> {
         let author = match updated.author {
             Some(user) => user,
-            None => return,
+            None => return Ok(()),
         };
 
         //ignore messages from MessageLogger
-        if author.bot && author.tag().as_str() == LOGGER_TAG {
-            return;
+        if self.is_self(author.id) {
+            return Ok(());
         }
 
-        let g_id = updated
-            .guild_id
-            .expect("message_update(): unable to get the guild_id!");
-
+        //edits in DMs are never logged
+        let g_id = match updated.guild_id {
+            Some(g_id) => g_id,
+            None => return Ok(()),
+        };
         let g_id_str = g_id.to_string();
 
         //ignore messages if logging not set up
-        if !(*self.map.lock().unwrap()).contains_key(&g_id_str) {
-            return;
+        let c_id = match self.log_channel_id(&g_id_str, LogCategory::EDITS) {
+            Some(c_id) => c_id,
+            None => return Ok(()),
+        };
+
+        //updates without content only change embeds or link previews
+        let updated_text = match updated.content {
+            Some(content) => content,
+            None => return Ok(()),
+        };
+
+        //get the last known version of the message and skip unchanged content
+        let previous_content = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(&updated.id)
+            .map(|cached| cached.content.clone());
+        if previous_content.as_ref() == Some(&updated_text) {
+            return Ok(());
         }
 
-        //get the channel id associated with the guild id
-        let c_id = *(*self.map.lock().unwrap())
-            .get(&g_id_str)
-            .expect("message_update(): unable to get the channel id from the hash map!");
-        //turn the c_id into a guild channel
-        let log_channel = ctx
-            .http
-            .get_channel(c_id)
-            .await
-            .expect("message_update(): unable to get the channel!")
-            .guild()
-            .expect("message_update(): unable to get the guild channel!");
-
-        //change c_id to be the updated message's channel id to link to message
-        //instead of the first log of the message
-        let link = "https://discord.com/channels/".to_owned()
-            + &g_id_str
-            + "/"
-            + c_id.to_string().as_str()
-            + "/"
-            + updated.id.to_string().as_str();
+        //ignore messages from ignored users, roles and bots, edit events
+        //don't say whether the message came from a webhook, the member is only
+        //fetched for edits that are logged
+        let member = g_id.member(ctx, author.id).await.ok();
+        let roles = match &member {
+            Some(m) => m.roles.clone(),
+            None => vec![],
+        };
+        if self.is_ignored_author(&g_id_str, &author, false, &roles) {
+            return Ok(());
+        }
 
-        //get the channel name to format it as: `#channel_name` in the embed
-        let channels = updated
-            .guild_id //.channel(&ctx).await
-            .expect("message_update(): unable to get the guild from the message!")
-            .channels(&ctx)
-            .await
-            .expect("message_update(): unable to get the channel from the guild");
-        let guild_channel = channels
-            .get(&updated.channel_id)
-            .expect("message_update(): unable to get guild from channel!");
-        let channel_name = "#".to_owned() + guild_channel.name();
-        let updated_text = updated
-            .content
-            .expect("message_update(): unable to get the updated message!");
-        let time = match updated
-            .timestamp {
-                Some(timestamp) => timestamp,
-                None => Timestamp::now()
-            };
-        let display_color = color_hash(&channel_name, &author.tag(), time);
-        let edited_time = updated
-            .edited_timestamp
-            .expect("message_update(): unable to get timestamp of edited message!");
+        //link to the edited message instead of the first log of the message
+        let link = updated.id.link(updated.channel_id, updated.guild_id);
 
-        let nickname = match author
-            .nick_in(&ctx, u64::from_str_radix(&g_id_str, 10).unwrap())
-            .await
+        //get the channel name to format it as: `#channel_name` in the embed
+        let guild_channel = match updated.channel_id.to_channel(ctx).await?.guild() {
+            Some(guild_channel) => guild_channel,
+            None => return Ok(()),
+        };
+        if !self.is_logged_channel(&guild_channel)
+            || self
+                .is_ignored_channel(ctx, &g_id_str, &guild_channel)
+                .await
         {
-            Some(nick) => nick,
-            None => author.name.clone(),
+            return Ok(());
+        }
+
+        //turn the c_id into a guild channel
+        let log_channel = fetch_log_channel(ctx, c_id).await?;
+        let upload_limit = self.upload_limit(ctx, log_channel.guild_id).await;
+
+        let channel_name = self.channel_title(ctx, &guild_channel).await;
+        let time = match updated.timestamp {
+            Some(timestamp) => timestamp,
+            None => Timestamp::now(),
+        };
+        let display_color = color_hash(&channel_name, &author.tag(), time);
+        let edited_time = match updated.edited_timestamp {
+            Some(timestamp) => timestamp,
+            None => Timestamp::now(),
         };
 
-        let face = match g_id.member(&ctx, author.id).await {
-            Ok(m) => match m.avatar_url() {
-                Some(url) => url,
-                None => author.face(),
-            },
-            Err(_) => author.face(),
+        let nickname = member_name(&author, member.as_ref().and_then(|m| m.nick.as_ref()));
+        let face = member_face(
+            g_id,
+            &author,
+            member.as_ref().and_then(|m| m.avatar.as_ref()),
+        );
+
+        if let Some(attachments) = &updated.attachments {
+            self.archive_attachments(ctx, g_id, updated.id, updated.channel_id, attachments)
+                .await;
+        }
+
+        //remember this version so the next edit can be compared against it
+        let voice_message = {
+            let mut cache = self.cache.lock().unwrap();
+            let attachments = match (&updated.attachments, cache.get(&updated.id)) {
+                (Some(attachments), _) => attachments.clone(),
+                (None, Some(cached)) => cached.attachments.clone(),
+                (None, None) => vec![],
+            };
+            //the flag isn't sent with edits
+            let voice_message = cache
+                .get(&updated.id)
+                .is_some_and(|cached| cached.voice_message);
+            cache.insert(
+                updated.id,
+                CachedMessage {
+                    msg_link: link.clone(),
+                    channel_name: channel_name.clone(),
+                    content: updated_text.clone(),
+                    color: display_color,
+                    author_id: author.id,
+                    author_name: nickname.clone(),
+                    author_face: face.clone(),
+                    attachments,
+                    voice_message,
+                },
+            );
+            voice_message
         };
 
         let send_info = LogInfo {
-            log_channel: log_channel,
+            log_channel,
             msg_link: link,
-            channel_name: channel_name,
+            channel_name,
             message_content: updated_text,
+            previous_content,
             message_type: MessageType::EDITED,
+            reply: None,
+            moderator: None,
             time_sent: edited_time,
             color: Color::new(display_color),
             author_name: nickname,
             author_face: face,
             attachments: updated.attachments,
-            ctx: ctx,
+            archived: self.archived_attachments(updated.id),
+            upload_limit,
+            voice_message,
+            ctx: ctx.clone(),
         };
 
-        log_message(send_info).await;
+        log_message(send_info).await
     }
-}
 
-#[tokio::main]
-async fn main() {
-    //check existence of the json file
-    let json_exists =
-        Path::try_exists(Path::new(JSON_PATH)).expect("unable to access the json file!");
+    async fn log_deleted(
+        &self,
+        ctx: &Context,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) -> LogResult<()> {
+        //deletions in DMs are never logged
+        let g_id_str = match guild_id {
+            Some(g_id) => g_id.to_string(),
+            None => return Ok(()),
+        };
+
+        //ignore deletions if logging not set up
+        let c_id = match self.log_channel_id(&g_id_str, LogCategory::EDITS) {
+            Some(c_id) => c_id,
+            None => return Ok(()),
+        };
+
+        //nothing is known about messages that were never cached
+        let deleted = match self.cache.lock().unwrap().remove(&deleted_message_id) {
+            Some(cached) => cached,
+            None => return Ok(()),
+        };
+
+        //turn the c_id into a guild channel
+        let log_channel = fetch_log_channel(ctx, c_id).await?;
+        let upload_limit = self.upload_limit(ctx, log_channel.guild_id).await;
+
+        let send_info = LogInfo {
+            log_channel,
+            msg_link: deleted.msg_link,
+            channel_name: deleted.channel_name,
+            message_content: deleted.content,
+            previous_content: None,
+            message_type: MessageType::DELETED,
+            reply: None,
+            moderator: None,
+            time_sent: Timestamp::now(),
+            color: Color::new(deleted.color),
+            author_name: deleted.author_name,
+            author_face: deleted.author_face,
+            attachments: Some(deleted.attachments),
+            archived: self.archived_attachments(deleted_message_id),
+            upload_limit,
+            voice_message: deleted.voice_message,
+            ctx: ctx.clone(),
+        };
 
-    //create json file if it doesn't exist
-    if !json_exists {
-        fs::write(JSON_PATH, "").expect("main(): unable to initialize json file!");
+        log_message(send_info).await
     }
 
+    async fn log_purged(
+        &self,
+        ctx: &Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) -> LogResult<()> {
+        let g_id = match guild_id {
+            Some(g_id) => g_id,
+            None => return Ok(()),
+        };
+
+        //ignore purges if logging not set up
+        let c_id = match self.log_channel_id(&g_id.to_string(), LogCategory::EDITS) {
+            Some(c_id) => c_id,
+            None => return Ok(()),
+        };
+
+        //collect whatever is still known about each deleted message
+        let mut report = String::new();
+        {
+            let mut cache = self.cache.lock().unwrap();
+            for id in &multiple_deleted_messages_ids {
+                let line = match cache.remove(id) {
+                    Some(cached) => purge_report_line(&cached),
+                    None => format!("{id}: (not cached)"),
+                };
+                report.push_str(&line);
+                report.push('\n');
+            }
+        }
+
+        //turn the c_id into a guild channel
+        let log_channel = fetch_log_channel(ctx, c_id).await?;
+
+        //get the channel name to format it as: `#channel_name` in the embed
+        let purged_channel = match channel_id.to_channel(ctx).await?.guild() {
+            Some(purged_channel) => purged_channel,
+            None => return Ok(()),
+        };
+        if !self.is_logged_channel(&purged_channel) {
+            return Ok(());
+        }
+
+        let purge_info = PurgeInfo {
+            log_channel,
+            channel_link: format!("https://discord.com/channels/{g_id}/{channel_id}"),
+            channel_name: self.channel_title(ctx, &purged_channel).await,
+            deleted_count: multiple_deleted_messages_ids.len(),
+            report,
+            ctx: ctx.clone(),
+        };
+
+        log_purge(purge_info).await
+    }
+}
+
+#[tokio::main]
+async fn main() {
     setup_bot().await;
 }
 
 async fn setup_bot() {
     //get the token from file
     let token = fs::read_to_string(DISCORD_AUTH_PATH).expect("could not read discord token file!");
-    let intents = GatewayIntents::GUILD_MESSAGES
+    let intents = GatewayIntents::GUILDS
+        | GatewayIntents::GUILD_MESSAGES
         | GatewayIntents::MESSAGE_CONTENT
-        | GatewayIntents::GUILD_MEMBERS;
+        | GatewayIntents::GUILD_MEMBERS
+        | GatewayIntents::GUILD_BANS
+        | GatewayIntents::GUILD_EMOJIS_AND_STICKERS
+        | GatewayIntents::GUILD_VOICE_STATES
+        | GatewayIntents::GUILD_MESSAGE_REACTIONS
+        | GatewayIntents::GUILD_INVITES;
 
     let handler = Handler::new();
 
@@ -402,52 +1294,79 @@ async fn setup_bot() {
     }
 }
 
-fn read_json() -> Result<HashMap<String, u64>, std::io::Error> {
-    //read from json file
-    let contents = fs::read_to_string(JSON_PATH)?;
+//reply to a slash command with a plain message
+async fn respond(
+    ctx: &Context,
+    slash_command: &ApplicationCommandInteraction,
+    content: String,
+) -> LogResult<()> {
+    slash_command
+        .create_interaction_response(ctx, |reply| {
+            reply.interaction_response_data(|message| message.content(content))
+        })
+        .await?;
+    Ok(())
+}
 
-    let map: Result<SaveMap, Error>;
-    //if the json file is empty, initialize the hash map
-    //deserialize existing json file otherwise
-    if contents == "" {
-        let save_map = SaveMap {
-            map: HashMap::new(),
-        };
-        map = Ok(save_map);
-    } else {
-        map = serde_json::from_str::<SaveMap>(&contents);
-    }
-    Ok(map.unwrap().map)
+//get the guild a slash command was used in
+fn command_guild(slash_command: &ApplicationCommandInteraction) -> LogResult<GuildId> {
+    slash_command.guild_id.ok_or_else(|| {
+        LogError::InvalidCommand(format!(
+            "/{} only works in servers",
+            slash_command.data.name
+        ))
+    })
 }
 
-fn write_json(save_map: &HashMap<String, u64>) -> Result<(), std::io::Error> {
-    //serialize and write to the json file
-    let serialized =
-        serde_json::to_string(&save_map).expect("write_json(): unable to serialize the save_map!");
-    fs::write(JSON_PATH, &serialized)?;
-    Ok(())
+//turn a log channel id into a guild channel
+async fn fetch_log_channel(ctx: &Context, c_id: u64) -> LogResult<GuildChannel> {
+    let c_id = ChannelId(c_id);
+    match ctx.http.get_channel(c_id.0).await {
+        Ok(channel) => channel.guild().ok_or(LogError::LogChannelMissing(c_id)),
+        Err(why) => Err(LogError::from_log_channel(why, c_id)),
+    }
 }
 
-//deletes entries in the json file if MessageLogger isn't in the server
-fn sync_json(guild_list: Vec<UnavailableGuild>) -> Result<(), std::io::Error> {
-    let mut map = read_json()?;
-    map.iter_mut().for_each(|pair| {
-        let mut exists = false;
-        //iterate through guild list
-        guild_list.iter().for_each(|guild| {
-            if !exists && &guild.id.0.to_string() == pair.0 {
-                exists = true;
-            }
-        });
-        //delete entry if not found in the guild list
-        if !exists {
-            delete_entry(&pair.0);
+//send a warning to the guild's system channel, or its owner if there is none
+async fn warn_guild(ctx: &Context, g_id: GuildId, warning: &str) -> LogResult<()> {
+    let guild = g_id.to_partial_guild(ctx).await?;
+    if let Some(system_channel) = guild.system_channel_id {
+        if system_channel.say(ctx, warning).await.is_ok() {
+            return Ok(());
         }
-    });
+    }
 
+    let owner_dm = guild.owner_id.create_dm_channel(ctx).await?;
+    owner_dm
+        .say(ctx, format!("in {}: {warning}", guild.name))
+        .await?;
     Ok(())
 }
 
+//make option a choice between the log categories
+fn add_category_choices(option: &mut CreateApplicationCommandOption) {
+    option
+        .name(CATEGORY_OPTION)
+        .kind(CommandOptionType::String)
+        .required(false)
+        .add_string_choice(ALL_CATEGORIES, ALL_CATEGORIES);
+    for category in LogCategory::ALL {
+        option.add_string_choice(category.name(), category.name());
+    }
+}
+
+//get the log category picked in a slash command, None means all categories
+fn category_option(slash_command: &ApplicationCommandInteraction) -> Option<LogCategory> {
+    slash_command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == CATEGORY_OPTION)
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .and_then(LogCategory::from_name)
+}
+
 //turn (#channel, @user, timestamp) into a color
 fn color_hash(channel_name: &String, user: &String, time: Timestamp) -> u32 {
     const TIMESTAMP_WEIGHT: u32 = 100;
@@ -469,131 +1388,458 @@ fn color_hash(channel_name: &String, user: &String, time: Timestamp) -> u32 {
     let hashed_val = (hasher.finish() as u32) + (TIMESTAMP_WEIGHT * secs_of_day);
 
     //get it as a u24 (get rid of first 8 bits)
-    let result = hashed_val & 0x00FFFFFF;
-    result
+    hashed_val & 0x00FFFFFF
 }
 
-//deletes the guild_id-channel_id pair from the json file if guild_id exists
-fn delete_entry(g_id: &String) -> Option<u64> {
-    let mut map = read_json().unwrap();
-    //remove the entry from the map if it exists
-    let return_val = map.remove(g_id);
-    //update the json file
-    write_json(&map).unwrap();
-    return_val
-}
+async fn log_message(log_info: LogInfo) -> LogResult<()> {
+    let attachments = log_info.attachments.as_deref().unwrap_or_default();
+    let images = extract_images(attachments);
+    let files = extract_nonimages(attachments);
+    let limit = log_info.upload_limit;
+
+    //files that can't be uploaded are described in embeds instead
+    let mut skipped: Vec<CreateEmbed> = vec![];
+
+    //archived images are uploaded and shown with `attachment://` urls, so
+    //they still show once discord's links stop working, images that don't
+    //fit in the message are shown from discord's links
+    let mut image_uploads: Vec<Upload> = vec![];
+    let mut image_urls: Vec<String> = vec![];
+    let mut uploaded_size = 0;
+    for (index, image) in images.iter().enumerate() {
+        let archived = archive::find_archived(image, &log_info.archived);
+        if image.size > limit {
+            let reason = format!("larger than the {} upload limit", file_size(limit));
+            skipped.push(uploads::skipped_embed(image, archived, &reason));
+        }
+        let bytes = match archived {
+            Some(archived) if uploaded_size + image.size <= limit => archived.read().await.ok(),
+            _ => None,
+        };
+        match bytes {
+            Some(bytes) => {
+                //images with the same name would show the same file
+                let filename = format!("{index}-{}", archive::upload_name(&image.filename));
+                image_urls.push(format!("attachment://{filename}"));
+                uploaded_size += bytes.len() as u64;
+                image_uploads.push(Upload {
+                    filename,
+                    bytes,
+                    attachment: image,
+                    archived,
+                });
+            }
+            None => image_urls.push(image.url.clone()),
+        }
+    }
 
-async fn log_message(log_info: LogInfo) {
-    let embed = create_embed(&log_info);
-    let attachments = match log_info.attachments {
-        Some(list) => list,
-        None => vec![],
-    };
+    //voice message lengths and text previews shown with the message
+    let mut attachment_fields: Vec<(String, String)> = vec![];
+    let mut file_uploads: Vec<Upload> = vec![];
+    for file in files {
+        let archived = archive::find_archived(file, &log_info.archived);
+        if file.size > limit {
+            let reason = format!("larger than the {} upload limit", file_size(limit));
+            skipped.push(uploads::skipped_embed(file, archived, &reason));
+            continue;
+        }
+        match uploads::attachment_bytes(file, archived).await {
+            Ok(bytes) => {
+                match AttachmentKind::of(file, log_info.voice_message) {
+                    AttachmentKind::VOICE => {
+                        if let Some(seconds) = media::voice_duration(&bytes) {
+                            let duration = format_duration(seconds as i64);
+                            attachment_fields.push(("voice message:".to_owned(), duration));
+                        }
+                    }
+                    AttachmentKind::TEXT => {
+                        if let Some(preview) = media::text_preview(&bytes) {
+                            let name = format!("{}:", excerpt(&file.filename, 200));
+                            attachment_fields.push((name, preview));
+                        }
+                    }
+                    _ => {}
+                }
+                file_uploads.push(Upload {
+                    filename: file.filename.clone(),
+                    bytes,
+                    attachment: file,
+                    archived,
+                });
+            }
+            Err(why) => {
+                let reason = format!("download failed: {why}");
+                skipped.push(uploads::skipped_embed(file, archived, &reason));
+            }
+        }
+    }
 
-    let images = extract_images(&attachments);
-    let files = extract_nonimages(&attachments);
+    //discord's links are used if the uploaded images are rejected
+    let discord_urls: Vec<String> = images.iter().map(|image| image.url.clone()).collect();
+    let mut messages = content_messages(&log_info, &image_urls, attachment_fields.clone());
+    //create_embeds always makes at least one embed
+    let last = messages.pop().unwrap_or_default();
+    for embeds in messages {
+        send_log(&log_info, embeds, vec![]).await?;
+    }
+
+    //the images have to be uploaded with the embeds that show them, other
+    //files join them while they fit
+    let mut last_uploads = image_uploads;
+    let more_files = uploads::batch(&mut last_uploads, file_uploads, limit);
+    if let Some(rejected) = send_log(&log_info, last, last_uploads).await? {
+        let mut retry = content_messages(&log_info, &discord_urls, attachment_fields);
+        send_log(&log_info, retry.pop().unwrap_or_default(), vec![]).await?;
+        skipped.extend(rejected);
+    }
+
+    //each batch is sent on its own so one rejected upload doesn't stop the rest
+    for batch in more_files {
+        let mut embed = CreateEmbed::default();
+        embed
+            .url(&log_info.msg_link)
+            .title(log_info.channel_name.clone() + " (more files)")
+            .color(log_info.color);
+        if let Some(rejected) = send_log(&log_info, vec![embed], batch).await? {
+            skipped.extend(rejected);
+        }
+    }
+    for embeds in skipped.chunks(uploads::MAX_EMBEDS) {
+        send_log(&log_info, embeds.to_vec(), vec![]).await?;
+    }
+    Ok(())
+}
+
+//the embeds of a logged message, every content embed goes in its own message
+//to stay under the character limit shared by all embeds of a message
+fn content_messages(
+    log_info: &LogInfo,
+    image_urls: &[String],
+    attachment_fields: Vec<(String, String)>,
+) -> Vec<Vec<CreateEmbed>> {
+    let embeds = create_embeds(log_info, image_urls, attachment_fields);
+    let mut messages: Vec<Vec<CreateEmbed>> = embeds.into_iter().map(|e| vec![e]).collect();
+    //set image embeds for all images except first
+    //because first image is part of the last content embed
+    if let Some(last) = messages.last_mut() {
+        for url in image_urls.iter().skip(1) {
+            last.push(create_image_embed(url, log_info.msg_link.clone()));
+        }
+        //videos are shown with a thumbnail while there is room
+        let attachments = log_info.attachments.as_deref().unwrap_or_default();
+        for video in attachments.iter().filter(|attachment| {
+            AttachmentKind::of(attachment, log_info.voice_message) == AttachmentKind::VIDEO
+        }) {
+            if last.len() < uploads::MAX_EMBEDS {
+                last.push(media::video_embed(video, &log_info.msg_link));
+            }
+        }
+    }
+    messages
+}
 
-    if files.len() == 0 {
+//send a message to the log channel, if discord rejects its files they are
+//returned as embeds describing them instead of failing the whole log
+async fn send_log(
+    log_info: &LogInfo,
+    embeds: Vec<CreateEmbed>,
+    files: Vec<Upload<'_>>,
+) -> LogResult<Option<Vec<CreateEmbed>>> {
+    let ctx = &log_info.ctx;
+    let c_id = log_info.log_channel.id;
+    if files.is_empty() {
         log_info
             .log_channel
-            .send_message(&log_info.ctx, |r| {
-                if images.len() <= 1 {
-                    return r.set_embed(embed);
-                } else {
-                    let mut embeds = vec![embed];
-                    //set image embeds for all images except first
-                    //because first image is part of the first embed
-                    for index in 1..images.len() {
-                        embeds.push(create_image_embed(
-                            &images[index],
-                            log_info.msg_link.clone(),
-                        ));
-                    }
-                    return r.add_embeds(embeds);
-                }
-            })
+            .send_message(ctx, |r| r.add_embeds(embeds))
             .await
-            .unwrap();
-    } else {
-        let mut to_upload: Vec<AttachmentType> = vec![];
-        for file in files {
-            let bytes = match file.download().await {
-                Ok(b) => b,
-                Err(_) => vec![],
-            };
+            .map_err(|why| LogError::from_log_channel(why, c_id))?;
+        return Ok(None);
+    }
 
-            let at: AttachmentType = AttachmentType::Bytes {
-                data: Cow::Owned(bytes),
-                filename: file.filename.clone(),
-            };
-            to_upload.push(at);
+    let described: Vec<_> = files
+        .iter()
+        .map(|file| (file.attachment, file.archived))
+        .collect();
+    let sent = log_info
+        .log_channel
+        .send_files(ctx, files, |r| r.add_embeds(embeds))
+        .await;
+    match sent.map_err(|why| LogError::from_log_channel(why, c_id)) {
+        Ok(_) => Ok(None),
+        //problems with the log channel itself are for the guild to fix
+        Err(why) if why.log_channel().is_some() => Err(why),
+        Err(why) => {
+            let reason = format!("upload rejected: {why}");
+            Ok(Some(
+                described
+                    .into_iter()
+                    .map(|(attachment, archived)| {
+                        uploads::skipped_embed(attachment, archived, &reason)
+                    })
+                    .collect(),
+            ))
         }
+    }
+}
 
-        log_info
+async fn log_purge(purge_info: PurgeInfo) -> LogResult<()> {
+    let embed = create_purge_embed(&purge_info);
+    let c_id = purge_info.log_channel.id;
+
+    let sent = if purge_info.report.chars().count() <= FIELD_LIMIT {
+        purge_info
             .log_channel
-            .send_files(&log_info.ctx, to_upload, |r| {
-                if images.len() <= 1 {
-                    return r.set_embed(embed);
-                } else {
-                    let mut embeds = vec![embed];
-                    //set image embeds for all images except first
-                    //because first image is part of the first embed
-                    for index in 1..images.len() {
-                        embeds.push(create_image_embed(
-                            &images[index],
-                            log_info.msg_link.clone(),
-                        ));
-                    }
-                    return r.add_embeds(embeds);
-                }
-            })
+            .send_message(&purge_info.ctx, |r| r.set_embed(embed))
             .await
-            .unwrap();
+    } else {
+        let report_file = AttachmentType::Bytes {
+            data: Cow::Owned(purge_info.report.into_bytes()),
+            filename: format!("purge-{}.txt", Timestamp::now().unix_timestamp()),
+        };
+
+        purge_info
+            .log_channel
+            .send_files(&purge_info.ctx, vec![report_file], |r| r.set_embed(embed))
+            .await
+    };
+    sent.map_err(|why| LogError::from_log_channel(why, c_id))?;
+    Ok(())
+}
+
+fn create_purge_embed(purge_info: &PurgeInfo) -> CreateEmbed {
+    let summary = format!("{} messages were deleted", purge_info.deleted_count);
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .url(&purge_info.channel_link)
+        .title(&purge_info.channel_name)
+        .field("purged:", summary, false)
+        .timestamp(Timestamp::now())
+        .color(Color::DARK_RED);
+
+    //only inline the report if it fits in a field, it is attached otherwise
+    if purge_info.report.chars().count() <= FIELD_LIMIT {
+        embed.field("messages:", &purge_info.report, false);
+    } else {
+        embed.field("messages:", "see the attached file", false);
+    }
+    embed
+}
+
+//the name a member is shown with, their nickname if they have one
+fn member_name(user: &User, nick: Option<&String>) -> String {
+    match nick {
+        Some(nick) => nick.clone(),
+        None => user.name.clone(),
     }
 }
 
-fn create_embed(log_info: &LogInfo) -> CreateEmbed {
+//the avatar a member is shown with, their server avatar if they have one
+fn member_face(g_id: GuildId, user: &User, avatar: Option<&String>) -> String {
+    match avatar {
+        Some(hash) => {
+            let ext = if hash.starts_with("a_") {
+                "gif"
+            } else {
+                "webp"
+            };
+            format!(
+                "https://cdn.discordapp.com/guilds/{g_id}/users/{}/avatars/{hash}.{ext}?size=1024",
+                user.id
+            )
+        }
+        None => user.face(),
+    }
+}
+
+//show a flag being switched
+fn on_off(before: bool, after: bool) -> String {
+    let name = |flag: bool| if flag { "on" } else { "off" };
+    format!("{} → {}", name(before), name(after))
+}
+
+//join mentions so they fit in one embed field, the rest are counted
+fn mention_list(mentions: &[String]) -> String {
+    let mut list = String::new();
+    for (index, mention) in mentions.iter().enumerate() {
+        let remaining = mentions.len() - index;
+        let more = format!(" and {remaining} more");
+        //always leave room to say how many did not fit
+        if list.chars().count() + mention.chars().count() + 1 + more.chars().count() > FIELD_LIMIT {
+            list.push_str(&more);
+            break;
+        }
+        if !list.is_empty() {
+            list.push(' ');
+        }
+        list.push_str(mention);
+    }
+    list
+}
+
+//format a cached message as one line of a purge report
+fn purge_report_line(cached: &CachedMessage) -> String {
+    let mut line = format!("{}: {}", cached.author_name, cached.content);
+    for attachment in &cached.attachments {
+        line.push_str(&format!(" [{}]", attachment.filename));
+    }
+    line
+}
+
+fn create_embeds(
+    log_info: &LogInfo,
+    image_urls: &[String],
+    attachment_fields: Vec<(String, String)>,
+) -> Vec<CreateEmbed> {
     let field_name = match &log_info.message_type {
-        MessageType::POSTED => "posted:",
-        MessageType::EDITED => "edited:",
+        MessageType::POSTED => "posted",
+        MessageType::EDITED => "edited",
+        MessageType::DELETED => "deleted",
+        MessageType::PINNED => "pinned",
+        MessageType::UNPINNED => "unpinned",
     };
 
-    let mut msg = log_info.message_content.clone();
-    if msg.len() > 1024 {
-        msg.truncate(1021);
-        msg = msg + "...";
+    let mut fields: Vec<(String, String)> = vec![];
+
+    //show both versions of an edited message if the old one is known
+    match &log_info.previous_content {
+        Some(previous) => {
+            let changes = diff::word_diff(previous, &log_info.message_content);
+            add_split_fields(&mut fields, "before", previous);
+            add_split_fields(&mut fields, "after", &log_info.message_content);
+            if let Some(changes) = changes {
+                add_split_fields(&mut fields, "changes", &changes);
+            }
+        }
+        None => add_split_fields(&mut fields, field_name, &log_info.message_content),
     }
 
-    let mut embed = CreateEmbed::default();
-    embed
-        .url(&log_info.msg_link)
-        .title(&log_info.channel_name)
-        .field(field_name, msg, false)
-        .timestamp(log_info.time_sent)
-        .color(log_info.color)
-        .author(|a| {
-            a.name(&log_info.author_name);
-            a.icon_url(&log_info.author_face)
-        });
-
-    //log the first image attachment in this embed so all images are logged
-    let images = extract_images(log_info.attachments.as_ref().unwrap());
-    if images.len() > 0 {
-        //set first image as this embed's displayed image
-        embed.field("with image(s):", "", false);
-        embed.image(images[0].url.clone());
+    //quote the message that was replied to
+    if let Some(reply) = &log_info.reply {
+        let ping = if reply.pinged {
+            "pinged"
+        } else {
+            "did not ping"
+        };
+        let quote = format!(
+            "**{}** ({ping}): {}\n[jump to message]({})",
+            reply.author_name, reply.excerpt, reply.msg_link
+        );
+        fields.push(("replying to:".to_owned(), quote));
     }
-    embed
+
+    if let Some(moderator) = &log_info.moderator {
+        fields.push((format!("{field_name} by:"), moderator.clone()));
+    }
+
+    fields.extend(attachment_fields);
+
+    if !image_urls.is_empty() {
+        fields.push(("with image(s):".to_owned(), String::new()));
+    }
+
+    //spread the fields over as many embeds as needed
+    let mut embeds: Vec<CreateEmbed> = vec![];
+    let mut pending = fields.into_iter().peekable();
+    while embeds.is_empty() || pending.peek().is_some() {
+        let title = if embeds.is_empty() {
+            log_info.channel_name.clone()
+        } else {
+            log_info.channel_name.clone() + " (continued)"
+        };
+
+        let mut embed = CreateEmbed::default();
+        embed
+            .url(&log_info.msg_link)
+            .title(&title)
+            .timestamp(log_info.time_sent)
+            .color(log_info.color)
+            .author(|a| {
+                a.name(&log_info.author_name);
+                a.icon_url(&log_info.author_face)
+            });
+
+        let mut size = title.chars().count() + log_info.author_name.chars().count();
+        let mut field_count = 0;
+        while let Some((name, value)) = pending.peek() {
+            let field_size = name.chars().count() + value.chars().count();
+            if field_count == EMBED_FIELD_LIMIT || size + field_size > EMBED_LIMIT {
+                break;
+            }
+            embed.field(name, value, false);
+            size += field_size;
+            field_count += 1;
+            pending.next();
+        }
+        embeds.push(embed);
+    }
+
+    //log the first image attachment in the last embed so all images are logged
+    if let (Some(embed), Some(url)) = (embeds.last_mut(), image_urls.first()) {
+        embed.image(url);
+    }
+    embeds
+}
+
+//add text as one or more fields, numbering the fields if it had to be split
+fn add_split_fields(fields: &mut Vec<(String, String)>, name: &str, text: &str) {
+    let chunks = split_field(text);
+    let total = chunks.len();
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let field_name = if total == 1 {
+            format!("{name}:")
+        } else {
+            format!("{name} ({}/{total}):", index + 1)
+        };
+        fields.push((field_name, chunk));
+    }
+}
+
+//split text into pieces that fit in a field, preferring to break on line
+//breaks and then whitespace, and never inside a multi-byte character
+fn split_field(text: &str) -> Vec<String> {
+    let mut chunks: Vec<String> = vec![];
+    let mut remaining = text;
+    while let Some((limit, _)) = remaining.char_indices().nth(FIELD_LIMIT) {
+        let candidate = &remaining[..limit];
+        let split = candidate
+            .rfind('\n')
+            .or_else(|| candidate.rfind(char::is_whitespace))
+            .filter(|&index| index > 0);
+
+        match split {
+            Some(index) => {
+                chunks.push(remaining[..index].to_owned());
+                //drop the character that was split on
+                let separator = remaining[index..].chars().next().map_or(0, char::len_utf8);
+                remaining = &remaining[index + separator..];
+            }
+            None => {
+                chunks.push(candidate.to_owned());
+                remaining = &remaining[limit..];
+            }
+        }
+    }
+    chunks.push(remaining.to_owned());
+    chunks
 }
 
-fn create_image_embed(attachment: &Attachment, url: String) -> CreateEmbed {
+//shorten text to at most max_chars characters for quoting
+fn excerpt(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_owned();
+    }
+    text.chars().take(max_chars).collect::<String>() + "..."
+}
+
+fn create_image_embed(image_url: &str, url: String) -> CreateEmbed {
     let mut embed = CreateEmbed::default();
     embed.url(url);
-    embed.image(attachment.url.clone()).clone()
+    embed.image(image_url).clone()
 }
 
 //create vector of only images from attachments
-fn extract_images(attachments: &Vec<Attachment>) -> Vec<&Attachment> {
+fn extract_images(attachments: &[Attachment]) -> Vec<&Attachment> {
     let mut files: Vec<&Attachment> = vec![];
     for attachment in attachments {
         if is_image(attachment) {
@@ -604,7 +1850,7 @@ fn extract_images(attachments: &Vec<Attachment>) -> Vec<&Attachment> {
 }
 
 //create vector of non-images from attachments
-fn extract_nonimages(attachments: &Vec<Attachment>) -> Vec<&Attachment> {
+fn extract_nonimages(attachments: &[Attachment]) -> Vec<&Attachment> {
     let mut files: Vec<&Attachment> = vec![];
     for attachment in attachments {
         if !is_image(attachment) {
@@ -615,11 +1861,36 @@ fn extract_nonimages(attachments: &Vec<Attachment>) -> Vec<&Attachment> {
 }
 
 fn is_image(attachment: &Attachment) -> bool {
-    attachment
-        .content_type
-        .as_ref()
-        .unwrap()
-        .split("/")
-        .collect::<Vec<&str>>()[0]
-        == "image"
+    //only audio depends on whether the message is a voice message
+    AttachmentKind::of(attachment, false) == AttachmentKind::IMAGE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excerpt_counts_characters() {
+        assert_eq!(excerpt("short", 10), "short");
+        assert_eq!(excerpt("ééééé", 3), "ééé...");
+    }
+
+    #[test]
+    fn split_field_never_splits_characters() {
+        let text = "é".repeat(FIELD_LIMIT + 500);
+        let chunks = split_field(&text);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), FIELD_LIMIT);
+        assert_eq!(chunks[1].chars().count(), 500);
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn split_field_prefers_line_breaks() {
+        let text = "a".repeat(1000) + "\n" + &"ü".repeat(100);
+        assert_eq!(split_field(&text), vec!["a".repeat(1000), "ü".repeat(100)]);
+
+        let exact = "ü".repeat(FIELD_LIMIT);
+        assert_eq!(split_field(&exact), vec![exact.clone()]);
+    }
 }
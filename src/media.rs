@@ -0,0 +1,295 @@
+use crate::FIELD_LIMIT;
+use serde::Deserialize;
+use serenity::builder::CreateEmbed;
+use serenity::http::request::RequestBuilder;
+use serenity::http::routing::RouteInfo;
+use serenity::model::channel::Attachment;
+use serenity::model::id::ChannelId;
+use serenity::model::id::MessageId;
+use serenity::prelude::*;
+
+//text files up to this size are previewed in the log
+const TEXT_PREVIEW_SIZE: usize = 64 * 1024;
+const TEXT_PREVIEW_LINES: usize = 10;
+
+//opus counts samples at 48kHz whatever the file was recorded at
+const OPUS_SAMPLE_RATE: u64 = 48000;
+
+//message flag of voice messages, which serenity doesn't know about yet
+const IS_VOICE_MESSAGE: u64 = 1 << 13;
+
+//ogg page header flag of the last page of a stream
+const END_OF_STREAM: u8 = 0x04;
+//bytes before the segment table of an ogg page
+const PAGE_HEADER_SIZE: usize = 27;
+
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "log", "csv", "json", "toml", "yaml", "yml", "xml", "html", "css", "ini", "cfg",
+    "rs", "py", "js", "ts", "c", "h", "cpp", "hpp", "cs", "java", "kt", "go", "rb", "php", "lua",
+    "sh", "bat", "ps1", "sql",
+];
+
+//the flags of a message as discord sends them
+#[derive(Deserialize)]
+struct RawFlags {
+    #[serde(default)]
+    flags: u64,
+}
+
+//how an attachment is shown in the log
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    IMAGE,
+    VIDEO,
+    AUDIO,
+    VOICE,
+    TEXT,
+    UNKNOWN,
+}
+
+impl AttachmentKind {
+    //go by the content type, or the file extension when discord didn't
+    //recognize the file, only audio files can be voice messages
+    pub fn of(attachment: &Attachment, voice_message: bool) -> AttachmentKind {
+        let content_type = attachment
+            .content_type
+            .as_deref()
+            .and_then(|content_type| content_type.split(';').next())
+            .unwrap_or_default();
+        let (top, sub) = content_type.split_once('/').unwrap_or_default();
+        match top {
+            "image" => AttachmentKind::IMAGE,
+            "video" => AttachmentKind::VIDEO,
+            "audio" if voice_message => AttachmentKind::VOICE,
+            "audio" => AttachmentKind::AUDIO,
+            "text" => AttachmentKind::TEXT,
+            "application" if matches!(sub, "json" | "xml" | "javascript" | "x-sh") => {
+                AttachmentKind::TEXT
+            }
+            _ => from_extension(&attachment.filename),
+        }
+    }
+}
+
+//embed showing the first frame of a video, which discord's media proxy
+//renders when asked for an image
+pub fn video_embed(video: &Attachment, msg_link: &str) -> CreateEmbed {
+    let separator = if video.proxy_url.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+    let mut embed = CreateEmbed::default();
+    embed
+        .url(msg_link)
+        .title(&video.filename)
+        .image(format!("{}{separator}format=jpeg", video.proxy_url));
+    embed
+}
+
+//the first lines of a small text file, in a code block that fits a field
+pub fn text_preview(bytes: &[u8]) -> Option<String> {
+    if bytes.len() > TEXT_PREVIEW_SIZE {
+        return None;
+    }
+    let text = std::str::from_utf8(bytes).ok()?;
+    let total = text.lines().count();
+    //keep the file from closing the code block early
+    let lines: Vec<String> = text
+        .lines()
+        .take(TEXT_PREVIEW_LINES)
+        .map(|line| line.replace("```", "`\u{200b}``"))
+        .collect();
+
+    let more = if total > TEXT_PREVIEW_LINES {
+        format!("\n{} more lines", total - TEXT_PREVIEW_LINES)
+    } else {
+        String::new()
+    };
+    let room = FIELD_LIMIT - "```\n\n```".len() - more.chars().count();
+    let preview = crate::excerpt(&lines.join("\n"), room - 3);
+    Some(format!("```\n{preview}\n```{more}"))
+}
+
+//length of an ogg opus file in seconds, from the sample position of the page
+//that ends the stream minus the samples skipped at the start
+pub fn voice_duration(bytes: &[u8]) -> Option<u64> {
+    let mut pre_skip: Option<u64> = None;
+    let mut end: Option<u64> = None;
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let header = bytes.get(offset..offset + PAGE_HEADER_SIZE)?;
+        if &header[..4] != b"OggS" {
+            return None;
+        }
+        let flags = header[5];
+        let position = u64::from_le_bytes(header[6..14].try_into().ok()?);
+        let segment_count = header[26] as usize;
+        let segments =
+            bytes.get(offset + PAGE_HEADER_SIZE..offset + PAGE_HEADER_SIZE + segment_count)?;
+        let body_start = offset + PAGE_HEADER_SIZE + segment_count;
+        let body_size: usize = segments.iter().map(|&size| size as usize).sum();
+        let body = bytes.get(body_start..body_start + body_size)?;
+
+        //the first packet of the stream says how many samples to skip
+        if pre_skip.is_none() && body.starts_with(b"OpusHead") {
+            pre_skip = Some(u16::from_le_bytes(body.get(10..12)?.try_into().ok()?) as u64);
+        }
+        if flags & END_OF_STREAM != 0 {
+            end = Some(position);
+        }
+        offset = body_start + body_size;
+    }
+    Some(end?.saturating_sub(pre_skip?) / OPUS_SAMPLE_RATE)
+}
+
+//whether a message is a voice message, only fetched for messages with audio
+//since serenity drops the flag
+pub async fn is_voice_message(
+    ctx: &Context,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    attachments: &[Attachment],
+) -> bool {
+    let has_audio = attachments
+        .iter()
+        .any(|attachment| AttachmentKind::of(attachment, true) == AttachmentKind::VOICE);
+    if !has_audio {
+        return false;
+    }
+
+    let request = RequestBuilder::new(RouteInfo::GetMessage {
+        channel_id: channel_id.0,
+        message_id: message_id.0,
+    })
+    .build();
+    match ctx.http.fire::<RawFlags>(request).await {
+        Ok(raw) => raw.flags & IS_VOICE_MESSAGE != 0,
+        Err(why) => {
+            eprintln!("is_voice_message(): unable to fetch message {message_id}: {why}");
+            false
+        }
+    }
+}
+
+fn from_extension(filename: &str) -> AttachmentKind {
+    let extension = match filename.rsplit_once('.') {
+        Some((_, extension)) => extension.to_lowercase(),
+        None => return AttachmentKind::UNKNOWN,
+    };
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "webp" => AttachmentKind::IMAGE,
+        "mp4" | "webm" | "mov" | "mkv" => AttachmentKind::VIDEO,
+        "mp3" | "wav" | "ogg" | "flac" | "m4a" | "opus" => AttachmentKind::AUDIO,
+        extension if TEXT_EXTENSIONS.contains(&extension) => AttachmentKind::TEXT,
+        _ => AttachmentKind::UNKNOWN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::attachment;
+
+    //an ogg page with a single segment, the checksum isn't checked
+    fn page(flags: u8, position: u64, body: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS".to_vec();
+        page.push(0);
+        page.push(flags);
+        page.extend_from_slice(&position.to_le_bytes());
+        page.extend_from_slice(&[0; 12]);
+        page.push(1);
+        page.push(body.len() as u8);
+        page.extend_from_slice(body);
+        page
+    }
+
+    fn opus_head(pre_skip: u16) -> Vec<u8> {
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 1]);
+        head.extend_from_slice(&pre_skip.to_le_bytes());
+        head.extend_from_slice(&48000u32.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+        head
+    }
+
+    #[test]
+    fn voice_duration_uses_end_of_stream_page() {
+        let mut bytes = page(0x02, 0, &opus_head(312));
+        bytes.extend(page(0, 0, b"OpusTags"));
+        //packet data that happens to contain a page marker
+        bytes.extend(page(0, 48000 * 5 + 312, b"xxOggSxxxxxxxxxxxxxxxxxxxx"));
+        bytes.extend(page(END_OF_STREAM, 48000 * 12 + 312, b"last packet"));
+        assert_eq!(voice_duration(&bytes), Some(12));
+    }
+
+    #[test]
+    fn voice_duration_rejects_broken_streams() {
+        let mut bytes = page(0x02, 0, &opus_head(312));
+        bytes.extend(page(0, 48000 * 5, b"packet"));
+        //no page ends the stream
+        assert_eq!(voice_duration(&bytes), None);
+
+        bytes.extend(page(END_OF_STREAM, 48000 * 6, b"packet"));
+        bytes.truncate(bytes.len() - 2);
+        assert_eq!(voice_duration(&bytes), None);
+        assert_eq!(voice_duration(b"not an ogg file at all, just text"), None);
+    }
+
+    #[test]
+    fn text_preview_escapes_code_blocks() {
+        let preview = text_preview(b"fn main() {}\n```\nend").unwrap();
+        assert!(preview.starts_with("```\n"));
+        assert!(preview.ends_with("\n```"));
+        assert_eq!(preview.matches("```").count(), 2);
+        assert!(preview.contains("`\u{200b}``"));
+    }
+
+    #[test]
+    fn text_preview_keeps_first_lines() {
+        let text: Vec<String> = (1..=15).map(|line| format!("line {line}")).collect();
+        let preview = text_preview(text.join("\n").as_bytes()).unwrap();
+        assert!(preview.contains("line 10\n```"));
+        assert!(!preview.contains("line 11"));
+        assert!(preview.ends_with("5 more lines"));
+    }
+
+    #[test]
+    fn text_preview_fits_a_field() {
+        let text = "é".repeat(5000);
+        let preview = text_preview(text.as_bytes()).unwrap();
+        assert!(preview.chars().count() <= FIELD_LIMIT);
+        assert_eq!(text_preview(&[0xff, 0xfe]), None);
+    }
+
+    #[test]
+    fn attachment_kinds() {
+        let kind = |filename, content_type| {
+            AttachmentKind::of(&attachment(filename, content_type, 100), false)
+        };
+        assert_eq!(kind("a.png", Some("image/png")), AttachmentKind::IMAGE);
+        assert_eq!(kind("a.mp4", Some("video/mp4")), AttachmentKind::VIDEO);
+        assert_eq!(kind("a.ogg", Some("audio/ogg")), AttachmentKind::AUDIO);
+        assert_eq!(
+            kind("a.txt", Some("text/plain; charset=utf-8")),
+            AttachmentKind::TEXT
+        );
+        assert_eq!(
+            kind("a.json", Some("application/json")),
+            AttachmentKind::TEXT
+        );
+        assert_eq!(kind("main.RS", None), AttachmentKind::TEXT);
+        assert_eq!(kind("blob", None), AttachmentKind::UNKNOWN);
+        assert_eq!(
+            kind("a.zip", Some("application/zip")),
+            AttachmentKind::UNKNOWN
+        );
+
+        let voice = attachment("voice-message.ogg", Some("audio/ogg"), 100);
+        assert_eq!(AttachmentKind::of(&voice, true), AttachmentKind::VOICE);
+        //only audio can be a voice message
+        let image = attachment("a.png", Some("image/png"), 100);
+        assert_eq!(AttachmentKind::of(&image, true), AttachmentKind::IMAGE);
+    }
+}
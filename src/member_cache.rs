@@ -0,0 +1,50 @@
+use serenity::model::guild::Member;
+use serenity::model::id::GuildId;
+use serenity::model::id::RoleId;
+use serenity::model::id::UserId;
+use serenity::model::Timestamp;
+use std::collections::HashMap;
+
+//what is remembered about a guild member, discord doesn't send it when they leave
+#[derive(Clone)]
+pub struct CachedMember {
+    pub nick: Option<String>,
+    //hash of the member's server avatar
+    pub avatar: Option<String>,
+    pub roles: Vec<RoleId>,
+    pub joined_at: Option<Timestamp>,
+    pub timed_out_until: Option<Timestamp>,
+}
+
+impl From<&Member> for CachedMember {
+    fn from(member: &Member) -> Self {
+        CachedMember {
+            nick: member.nick.clone(),
+            avatar: member.avatar.clone(),
+            roles: member.roles.clone(),
+            joined_at: member.joined_at,
+            timed_out_until: member.communication_disabled_until,
+        }
+    }
+}
+
+//members of every guild, filled in as guilds become available and members join
+#[derive(Default)]
+pub struct MemberCache {
+    guilds: HashMap<GuildId, HashMap<UserId, CachedMember>>,
+}
+
+impl MemberCache {
+    pub fn insert(&mut self, g_id: GuildId, user_id: UserId, member: CachedMember) {
+        self.guilds.entry(g_id).or_default().insert(user_id, member);
+    }
+
+    pub fn get(&self, g_id: GuildId, user_id: UserId) -> Option<&CachedMember> {
+        self.guilds.get(&g_id)?.get(&user_id)
+    }
+
+    //take a member out of the cache, returning them if they were cached
+    pub fn remove(&mut self, g_id: GuildId, user_id: UserId) -> Option<CachedMember> {
+        self.guilds.get_mut(&g_id)?.remove(&user_id)
+    }
+}
@@ -0,0 +1,274 @@
+use crate::audit_log;
+use crate::config::LogCategory;
+use crate::error::LogResult;
+use crate::member_cache::CachedMember;
+use crate::member_face;
+use crate::member_name;
+use crate::mention_list;
+use crate::moderation::ModAction;
+use crate::Handler;
+use serenity::builder::CreateEmbed;
+use serenity::client::bridge::gateway::ChunkGuildFilter;
+use serenity::model::event::GuildMemberUpdateEvent;
+use serenity::model::event::GuildMembersChunkEvent;
+use serenity::model::guild::audit_log::Action;
+use serenity::model::guild::audit_log::MemberAction;
+use serenity::model::guild::Guild;
+use serenity::model::guild::Member;
+use serenity::model::id::GuildId;
+use serenity::model::user::User;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use serenity::utils::Color;
+
+//accounts younger than this are flagged when they join
+const NEW_ACCOUNT_DAYS: i64 = 7;
+const SECONDS_IN_DAY: i64 = 86400;
+
+impl Handler {
+    //remember the members of a guild once it becomes available
+    pub fn load_members(&self, ctx: &Context, guild: &Guild) {
+        {
+            let mut members = self.members.lock().unwrap();
+            for (user_id, member) in &guild.members {
+                members.insert(guild.id, *user_id, CachedMember::from(member));
+            }
+        }
+        //large guilds are sent with hardly any members, the rest have to be
+        //requested and arrive in chunks
+        if (guild.members.len() as u64) < guild.member_count {
+            ctx.shard
+                .chunk_guild(guild.id, None, ChunkGuildFilter::None, None);
+        }
+    }
+
+    //remember the members of a large guild as they arrive
+    pub fn load_member_chunk(&self, chunk: &GuildMembersChunkEvent) {
+        let mut members = self.members.lock().unwrap();
+        for (user_id, member) in &chunk.members {
+            members.insert(chunk.guild_id, *user_id, CachedMember::from(member));
+        }
+    }
+
+    pub async fn log_member_join(&self, ctx: &Context, member: Member) -> LogResult<()> {
+        let g_id = member.guild_id;
+        self.members
+            .lock()
+            .unwrap()
+            .insert(g_id, member.user.id, CachedMember::from(&member));
+
+        //ignore joins if logging not set up
+        if !self.is_logged(g_id, LogCategory::MEMBERS) {
+            return Ok(());
+        }
+
+        let user = &member.user;
+        let created_at = user.created_at();
+        let account_age = Timestamp::now().unix_timestamp() - created_at.unix_timestamp();
+        let is_new = account_age < NEW_ACCOUNT_DAYS * SECONDS_IN_DAY;
+
+        let mut embed = member_embed(user, "member joined:");
+        embed
+            .field("account created:", relative_time(created_at), false)
+            .color(if is_new {
+                Color::ORANGE
+            } else {
+                Color::DARK_GREEN
+            });
+        if is_new {
+            embed.field(
+                "new account:",
+                format!("this account is less than {NEW_ACCOUNT_DAYS} days old!"),
+                false,
+            );
+        }
+        if let Some(invite) = self.used_invite(ctx, g_id).await {
+            embed.field("invite:", invite, false);
+        }
+
+        self.log_event(ctx, g_id, LogCategory::MEMBERS, embed).await
+    }
+
+    pub async fn log_member_leave(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        user: User,
+    ) -> LogResult<()> {
+        let cached = self.members.lock().unwrap().remove(g_id, user.id);
+
+        //ignore leaves if logging not set up
+        if !self.is_logged(g_id, LogCategory::MEMBERS)
+            && !self.is_logged(g_id, LogCategory::MODERATION)
+        {
+            return Ok(());
+        }
+
+        //kicked and banned members leave too, only voluntary leaves are
+        //logged here
+        let removal = [
+            Action::Member(MemberAction::Kick),
+            Action::Member(MemberAction::BanAdd),
+        ];
+        match audit_log::find_entry(ctx, g_id, &removal, user.id.0).await {
+            Some(entry) if matches!(entry.action, Action::Member(MemberAction::Kick)) => {
+                return self
+                    .log_mod_action(ctx, g_id, &user, ModAction::KICK, Some(entry))
+                    .await;
+            }
+            //bans are logged by guild_ban_addition
+            Some(_) => return Ok(()),
+            None => {}
+        }
+
+        let mut embed = member_embed(&user, "member left:");
+        embed
+            .field("account created:", relative_time(user.created_at()), false)
+            .color(Color::DARK_RED);
+        match cached {
+            Some(cached) => {
+                if let Some(joined_at) = cached.joined_at {
+                    embed.field("joined:", relative_time(joined_at), false);
+                }
+                let roles: Vec<String> = cached
+                    .roles
+                    .iter()
+                    .map(|role_id| format!("<@&{role_id}>"))
+                    .collect();
+                let roles = if roles.is_empty() {
+                    "none".to_owned()
+                } else {
+                    mention_list(&roles)
+                };
+                embed.field("roles:", roles, false);
+            }
+            None => {
+                embed.field("roles:", "unknown", false);
+            }
+        }
+
+        self.log_event(ctx, g_id, LogCategory::MEMBERS, embed).await
+    }
+
+    //keep the cached member up to date, returning what was cached before
+    pub fn update_member(&self, updated: &GuildMemberUpdateEvent) -> Option<CachedMember> {
+        let mut members = self.members.lock().unwrap();
+        let previous = members.get(updated.guild_id, updated.user.id).cloned();
+        members.insert(
+            updated.guild_id,
+            updated.user.id,
+            CachedMember {
+                nick: updated.nick.clone(),
+                avatar: updated.avatar.clone(),
+                roles: updated.roles.clone(),
+                joined_at: Some(updated.joined_at),
+                timed_out_until: updated.communication_disabled_until,
+            },
+        );
+        previous
+    }
+
+    //log nickname, role and server avatar changes against the cached member
+    pub async fn log_member_update(
+        &self,
+        ctx: &Context,
+        updated: &GuildMemberUpdateEvent,
+        previous: Option<&CachedMember>,
+    ) -> LogResult<()> {
+        let g_id = updated.guild_id;
+        //nothing to compare against for members that were never cached
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+
+        //ignore member updates if logging not set up
+        if !self.is_logged(g_id, LogCategory::MEMBERS) {
+            return Ok(());
+        }
+
+        let user = &updated.user;
+        let added: Vec<String> = updated
+            .roles
+            .iter()
+            .filter(|role_id| !previous.roles.contains(role_id))
+            .map(|role_id| format!("<@&{role_id}>"))
+            .collect();
+        let removed: Vec<String> = previous
+            .roles
+            .iter()
+            .filter(|role_id| !updated.roles.contains(role_id))
+            .map(|role_id| format!("<@&{role_id}>"))
+            .collect();
+        let nick_changed = previous.nick != updated.nick;
+        let avatar_changed = previous.avatar != updated.avatar;
+        if added.is_empty() && removed.is_empty() && !nick_changed && !avatar_changed {
+            return Ok(());
+        }
+
+        let face = member_face(g_id, user, updated.avatar.as_ref());
+        let mut embed = CreateEmbed::default();
+        embed
+            .author(|author| {
+                author
+                    .name(member_name(user, updated.nick.as_ref()))
+                    .icon_url(&face)
+            })
+            .field("member updated:", format!("<@{}>", user.id), false)
+            .footer(|footer| footer.text(format!("user id: {}", user.id)))
+            .timestamp(Timestamp::now())
+            .color(Color::BLUE);
+
+        if nick_changed {
+            let before = member_name(user, previous.nick.as_ref());
+            let after = member_name(user, updated.nick.as_ref());
+            embed
+                .field("nickname before:", before, true)
+                .field("nickname after:", after, true);
+        }
+        if !added.is_empty() {
+            embed.field("roles added:", mention_list(&added), false);
+        }
+        if !removed.is_empty() {
+            embed.field("roles removed:", mention_list(&removed), false);
+        }
+        if !added.is_empty() || !removed.is_empty() {
+            let role_update = [Action::Member(MemberAction::RoleUpdate)];
+            let changed_by = match audit_log::find_entry(ctx, g_id, &role_update, user.id.0).await {
+                Some(entry) => entry.moderator_mention(),
+                None => "unknown".to_owned(),
+            };
+            embed.field("roles changed by:", changed_by, false);
+        }
+        if avatar_changed {
+            let before = member_face(g_id, user, previous.avatar.as_ref());
+            embed
+                .field(
+                    "server avatar:",
+                    format!("[before]({before}) → [after]({face})"),
+                    false,
+                )
+                .thumbnail(&face);
+        }
+
+        self.log_event(ctx, g_id, LogCategory::MEMBERS, embed).await
+    }
+}
+
+//embed about a member with their avatar and id
+fn member_embed(user: &User, title: &str) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .author(|author| author.name(user.tag()).icon_url(user.face()))
+        .thumbnail(user.face())
+        .field(title, format!("<@{}>", user.id), false)
+        .footer(|footer| footer.text(format!("user id: {}", user.id)))
+        .timestamp(Timestamp::now());
+    embed
+}
+
+//show a time as a date and how long ago it was, in the reader's timezone
+fn relative_time(time: Timestamp) -> String {
+    let secs = time.unix_timestamp();
+    format!("<t:{secs}:D> (<t:{secs}:R>)")
+}
@@ -0,0 +1,63 @@
+use serenity::model::id::MessageId;
+use serenity::model::id::UserId;
+use serenity::model::prelude::Attachment;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+//what is remembered about a message after it has been logged
+#[derive(Clone)]
+pub struct CachedMessage {
+    pub msg_link: String,
+    pub channel_name: String,
+    pub content: String,
+    pub color: u32,
+    pub author_id: UserId,
+    pub author_name: String,
+    pub author_face: String,
+    pub attachments: Vec<Attachment>,
+    pub voice_message: bool,
+}
+
+//bounded store of recently seen messages, oldest entries are evicted first
+pub struct MessageCache {
+    capacity: usize,
+    order: VecDeque<MessageId>,
+    messages: HashMap<MessageId, CachedMessage>,
+}
+
+impl MessageCache {
+    pub fn new(capacity: usize) -> Self {
+        MessageCache {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            messages: HashMap::with_capacity(capacity),
+        }
+    }
+
+    //add a message to the cache, replacing it if it is already cached
+    pub fn insert(&mut self, id: MessageId, message: CachedMessage) {
+        if self.messages.insert(id, message).is_none() {
+            self.order.push_back(id);
+        }
+
+        //evict the oldest messages once the cache is full
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.messages.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn get(&self, id: &MessageId) -> Option<&CachedMessage> {
+        self.messages.get(id)
+    }
+
+    //take a message out of the cache, returning it if it was cached
+    pub fn remove(&mut self, id: &MessageId) -> Option<CachedMessage> {
+        let removed = self.messages.remove(id);
+        if removed.is_some() {
+            self.order.retain(|cached_id| cached_id != id);
+        }
+        removed
+    }
+}
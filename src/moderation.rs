@@ -0,0 +1,142 @@
+use crate::audit_log;
+use crate::audit_log::AuditEntry;
+use crate::config::LogCategory;
+use crate::error::LogResult;
+use crate::member_cache::CachedMember;
+use crate::Handler;
+use serenity::builder::CreateEmbed;
+use serenity::model::event::GuildMemberUpdateEvent;
+use serenity::model::guild::audit_log::Action;
+use serenity::model::guild::audit_log::MemberAction;
+use serenity::model::id::GuildId;
+use serenity::model::user::User;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use serenity::utils::Color;
+
+//the moderation actions that get logged
+#[allow(clippy::upper_case_acronyms)]
+pub enum ModAction {
+    BAN,
+    UNBAN,
+    KICK,
+    TIMEOUT(Timestamp),
+    UNTIMEOUT,
+}
+
+impl ModAction {
+    fn audit_action(&self) -> Action {
+        Action::Member(match self {
+            ModAction::BAN => MemberAction::BanAdd,
+            ModAction::UNBAN => MemberAction::BanRemove,
+            ModAction::KICK => MemberAction::Kick,
+            ModAction::TIMEOUT(_) | ModAction::UNTIMEOUT => MemberAction::Update,
+        })
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            ModAction::BAN => "member banned",
+            ModAction::UNBAN => "member unbanned",
+            ModAction::KICK => "member kicked",
+            ModAction::TIMEOUT(_) => "member timed out",
+            ModAction::UNTIMEOUT => "timeout removed",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            ModAction::BAN => Color::RED,
+            ModAction::KICK => Color::DARK_ORANGE,
+            ModAction::TIMEOUT(_) => Color::GOLD,
+            ModAction::UNBAN | ModAction::UNTIMEOUT => Color::TEAL,
+        }
+    }
+}
+
+impl Handler {
+    //log a moderation action, finding who did it in the audit log unless
+    //it was already found
+    pub async fn log_mod_action(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        user: &User,
+        action: ModAction,
+        entry: Option<AuditEntry>,
+    ) -> LogResult<()> {
+        //ignore moderation actions if logging not set up
+        if !self.is_logged(g_id, LogCategory::MODERATION) {
+            return Ok(());
+        }
+
+        let entry = match entry {
+            Some(entry) => Some(entry),
+            None => audit_log::find_entry(ctx, g_id, &[action.audit_action()], user.id.0).await,
+        };
+        let embed = create_mod_embed(user, &action, entry.as_ref());
+        self.log_event(ctx, g_id, LogCategory::MODERATION, embed)
+            .await
+    }
+
+    //log a member being timed out or having their timeout removed early,
+    //timeouts that run out on their own don't send an update
+    pub async fn log_timeout(
+        &self,
+        ctx: &Context,
+        updated: &GuildMemberUpdateEvent,
+        previous: Option<&CachedMember>,
+    ) -> LogResult<()> {
+        //without the member cached there is no telling whether a timeout is
+        //new or was already there
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+
+        let now = Timestamp::now().unix_timestamp();
+        let before = previous
+            .timed_out_until
+            .filter(|until| until.unix_timestamp() > now);
+        let after = updated
+            .communication_disabled_until
+            .filter(|until| until.unix_timestamp() > now);
+
+        let action = match (before, after) {
+            (before, Some(until)) if before != Some(until) => ModAction::TIMEOUT(until),
+            (Some(_), None) => ModAction::UNTIMEOUT,
+            _ => return Ok(()),
+        };
+        self.log_mod_action(ctx, updated.guild_id, &updated.user, action, None)
+            .await
+    }
+}
+
+//moderation actions are titled by the action, unlike message logs
+fn create_mod_embed(user: &User, action: &ModAction, entry: Option<&AuditEntry>) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(action.title())
+        .author(|author| author.name(user.tag()).icon_url(user.face()))
+        .field("member:", format!("<@{}>", user.id), true)
+        .footer(|footer| footer.text(format!("user id: {}", user.id)))
+        .timestamp(Timestamp::now())
+        .color(action.color());
+
+    if let ModAction::TIMEOUT(until) = action {
+        let secs = until.unix_timestamp();
+        embed.field("until:", format!("<t:{secs}:f> (<t:{secs}:R>)"), true);
+    }
+
+    match entry {
+        Some(entry) => {
+            embed
+                .field("moderator:", entry.moderator_mention(), true)
+                .field("reason:", entry.reason_or_default(), false);
+        }
+        None => {
+            embed.field("moderator:", "unknown", true);
+        }
+    }
+    embed
+}
@@ -0,0 +1,128 @@
+use crate::audit_log;
+use crate::color_hash;
+use crate::config::LogCategory;
+use crate::error::LogResult;
+use crate::excerpt;
+use crate::fetch_log_channel;
+use crate::log_message;
+use crate::member_face;
+use crate::member_name;
+use crate::Handler;
+use crate::LogInfo;
+use crate::MessageType;
+use serenity::model::event::ChannelPinsUpdateEvent;
+use serenity::model::guild::audit_log::Action;
+use serenity::model::guild::audit_log::MessageAction;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use serenity::utils::Color;
+
+//how much of a pinned message is quoted in the log
+const PIN_EXCERPT_LENGTH: usize = 300;
+
+impl Handler {
+    //the event only says that the pins of a channel changed, the audit log
+    //says which message was pinned or unpinned and by whom
+    pub async fn log_pins_update(
+        &self,
+        ctx: &Context,
+        pins: ChannelPinsUpdateEvent,
+    ) -> LogResult<()> {
+        let g_id = match pins.guild_id {
+            Some(g_id) => g_id,
+            None => return Ok(()),
+        };
+        let g_id_str = g_id.to_string();
+
+        //ignore pins if logging not set up
+        let c_id = match self.log_channel_id(&g_id_str, LogCategory::EDITS) {
+            Some(c_id) => c_id,
+            None => return Ok(()),
+        };
+
+        //pins in ignored channels and threads that aren't logged are skipped
+        //before looking through the audit log
+        let guild_channel = match pins.channel_id.to_channel(ctx).await?.guild() {
+            Some(guild_channel) => guild_channel,
+            None => return Ok(()),
+        };
+        if !self.is_logged_channel(&guild_channel)
+            || self
+                .is_ignored_channel(ctx, &g_id_str, &guild_channel)
+                .await
+        {
+            return Ok(());
+        }
+
+        let actions = [
+            Action::Message(MessageAction::Pin),
+            Action::Message(MessageAction::Unpin),
+        ];
+        let entry = match audit_log::find_in_channel(ctx, g_id, &actions, pins.channel_id).await {
+            Some(entry) => entry,
+            None => {
+                eprintln!(
+                    "log_pins_update(): unable to find which message was pinned in {}",
+                    pins.channel_id
+                );
+                return Ok(());
+            }
+        };
+        let message_id = match entry.message_id {
+            Some(message_id) => message_id,
+            None => return Ok(()),
+        };
+        let message_type = match entry.action {
+            Action::Message(MessageAction::Unpin) => MessageType::UNPINNED,
+            _ => MessageType::PINNED,
+        };
+
+        let msg = ctx
+            .http
+            .get_message(pins.channel_id.0, message_id.0)
+            .await?;
+        let author = &msg.author;
+        let member = g_id.member(ctx, author.id).await.ok();
+
+        //ignore pinned messages from ignored users, roles, bots and webhooks
+        let roles = match &member {
+            Some(member) => member.roles.clone(),
+            None => vec![],
+        };
+        if self.is_ignored_author(&g_id_str, author, msg.webhook_id.is_some(), &roles) {
+            return Ok(());
+        }
+
+        //get the channel name to format it as: `#channel_name` in the embed
+        let channel_name = self.channel_title(ctx, &guild_channel).await;
+        let nickname = member_name(author, member.as_ref().and_then(|m| m.nick.as_ref()));
+        let face = member_face(
+            g_id,
+            author,
+            member.as_ref().and_then(|m| m.avatar.as_ref()),
+        );
+
+        let log_channel = fetch_log_channel(ctx, c_id).await?;
+        let send_info = LogInfo {
+            log_channel,
+            msg_link: msg.link(),
+            channel_name: channel_name.clone(),
+            message_content: excerpt(&msg.content, PIN_EXCERPT_LENGTH),
+            previous_content: None,
+            message_type,
+            reply: None,
+            moderator: Some(entry.moderator_mention()),
+            time_sent: Timestamp::now(),
+            color: Color::new(color_hash(&channel_name, &author.tag(), msg.timestamp)),
+            author_name: nickname,
+            author_face: face,
+            attachments: None,
+            archived: vec![],
+            upload_limit: 0,
+            voice_message: false,
+            ctx: ctx.clone(),
+        };
+
+        log_message(send_info).await
+    }
+}
@@ -0,0 +1,174 @@
+use crate::config::LogCategory;
+use crate::config::Toggle;
+use crate::error::LogResult;
+use crate::excerpt;
+use crate::Handler;
+use crate::FIELD_LIMIT;
+use serenity::builder::CreateEmbed;
+use serenity::model::channel::Reaction;
+use serenity::model::id::ChannelId;
+use serenity::model::id::GuildId;
+use serenity::model::id::MessageId;
+use serenity::model::id::UserId;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use serenity::utils::Color;
+use std::time::Duration;
+
+//reactions removed this soon after being added are logged as one entry
+const REACTION_GROUP_SECS: u64 = 30;
+
+//a reaction by one user to one message, the emoji as it is displayed
+pub type ReactionKey = (MessageId, UserId, String);
+
+impl Handler {
+    //reactions are held back for a while in case they are removed again
+    pub async fn log_reaction_add(&self, ctx: &Context, reaction: Reaction) -> LogResult<()> {
+        let (g_id, user_id) = match self.logged_reaction(&reaction) {
+            Some(logged) => logged,
+            None => return Ok(()),
+        };
+
+        let key: ReactionKey = (reaction.message_id, user_id, reaction.emoji.to_string());
+        let added_at = Timestamp::now();
+        self.reactions.lock().unwrap().insert(key.clone(), added_at);
+        tokio::time::sleep(Duration::from_secs(REACTION_GROUP_SECS)).await;
+
+        //the reaction was removed in the meantime and logged along with it,
+        //if it was added again since then the later add logs it
+        {
+            let mut reactions = self.reactions.lock().unwrap();
+            if reactions.get(&key) != Some(&added_at) {
+                return Ok(());
+            }
+            reactions.remove(&key);
+        }
+
+        let mut embed = reaction_embed(g_id, reaction.channel_id, reaction.message_id);
+        embed
+            .field("reaction added:", &key.2, true)
+            .field("by:", format!("<@{user_id}>"), true)
+            .color(Color::DARK_GREEN);
+        self.log_event(ctx, g_id, LogCategory::REACTIONS, embed)
+            .await
+    }
+
+    pub async fn log_reaction_remove(&self, ctx: &Context, reaction: Reaction) -> LogResult<()> {
+        let (g_id, user_id) = match self.logged_reaction(&reaction) {
+            Some(logged) => logged,
+            None => return Ok(()),
+        };
+
+        let key: ReactionKey = (reaction.message_id, user_id, reaction.emoji.to_string());
+        let added_at = self.reactions.lock().unwrap().remove(&key);
+
+        let mut embed = reaction_embed(g_id, reaction.channel_id, reaction.message_id);
+        embed.field("by:", format!("<@{user_id}>"), true);
+        match added_at {
+            Some(added_at) => {
+                let seconds = Timestamp::now().unix_timestamp() - added_at.unix_timestamp();
+                embed
+                    .field("reaction added and removed:", &key.2, true)
+                    .field("removed after:", format!("{seconds}s"), true)
+                    .color(Color::ORANGE);
+            }
+            None => {
+                embed
+                    .field("reaction removed:", &key.2, true)
+                    .color(Color::DARK_RED);
+            }
+        }
+        self.log_event(ctx, g_id, LogCategory::REACTIONS, embed)
+            .await
+    }
+
+    pub async fn log_reaction_remove_all(
+        &self,
+        ctx: &Context,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> LogResult<()> {
+        //the event doesn't say which guild the message is in
+        let cached = self
+            .channels
+            .lock()
+            .unwrap()
+            .get(&channel_id)
+            .map(|channel| channel.guild_id);
+        let g_id = match cached {
+            Some(g_id) => g_id,
+            None => match channel_id.to_channel(ctx).await?.guild() {
+                Some(channel) => channel.guild_id,
+                None => return Ok(()),
+            },
+        };
+
+        //reactions that were still held back are logged here instead
+        let mut recent: Vec<String> = vec![];
+        self.reactions
+            .lock()
+            .unwrap()
+            .retain(|(pending_id, user_id, emoji), _| {
+                if *pending_id != message_id {
+                    return true;
+                }
+                recent.push(format!("{emoji} by <@{user_id}>"));
+                false
+            });
+
+        if !self.is_enabled(g_id, Toggle::REACTIONS)
+            || !self.is_logged(g_id, LogCategory::REACTIONS)
+        {
+            return Ok(());
+        }
+
+        let mut embed = reaction_embed(g_id, channel_id, message_id);
+        embed
+            .field(
+                "all reactions removed:",
+                "every reaction was cleared",
+                false,
+            )
+            .color(Color::DARK_RED);
+        if !recent.is_empty() {
+            embed.field(
+                "recently added:",
+                excerpt(&recent.join("\n"), FIELD_LIMIT - 3),
+                false,
+            );
+        }
+        self.log_event(ctx, g_id, LogCategory::REACTIONS, embed)
+            .await
+    }
+
+    //the guild and user of a reaction that should be logged
+    fn logged_reaction(&self, reaction: &Reaction) -> Option<(GuildId, UserId)> {
+        let g_id = reaction.guild_id?;
+        let user_id = reaction.user_id?;
+        //reactions are noisy, they are only logged when the guild turned them on
+        if self.is_self(user_id)
+            || !self.is_enabled(g_id, Toggle::REACTIONS)
+            || !self.is_logged(g_id, LogCategory::REACTIONS)
+        {
+            return None;
+        }
+        Some((g_id, user_id))
+    }
+}
+
+//embed linking to the message that was reacted to
+fn reaction_embed(g_id: GuildId, channel_id: ChannelId, message_id: MessageId) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .field(
+            "message:",
+            format!(
+                "[jump to message]({})",
+                message_id.link(channel_id, Some(g_id))
+            ),
+            false,
+        )
+        .footer(|footer| footer.text(format!("message id: {message_id}")))
+        .timestamp(Timestamp::now());
+    embed
+}
@@ -0,0 +1,184 @@
+use crate::audit_log;
+use crate::config::LogCategory;
+use crate::diff::permission_diff;
+use crate::error::LogResult;
+use crate::excerpt;
+use crate::on_off;
+use crate::Change;
+use crate::Handler;
+use crate::FIELD_LIMIT;
+use serenity::builder::CreateEmbed;
+use serenity::model::guild::audit_log::Action;
+use serenity::model::guild::audit_log::RoleAction;
+use serenity::model::guild::Guild;
+use serenity::model::guild::Role;
+use serenity::model::id::GuildId;
+use serenity::model::id::RoleId;
+use serenity::model::permissions::Permissions;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+
+impl Handler {
+    //remember every role of a guild once it becomes available
+    pub fn load_roles(&self, guild: &Guild) {
+        let mut roles = self.roles.lock().unwrap();
+        for (role_id, role) in &guild.roles {
+            roles.insert(*role_id, role.clone());
+        }
+    }
+
+    pub async fn log_role_create(&self, ctx: &Context, created: Role) -> LogResult<()> {
+        self.roles
+            .lock()
+            .unwrap()
+            .insert(created.id, created.clone());
+
+        //ignore role changes if logging not set up
+        if !self.is_logged(created.guild_id, LogCategory::ROLES) {
+            return Ok(());
+        }
+
+        let mut embed = role_embed(ctx, &created, Change::CREATED).await;
+        embed.field("permissions:", permission_list(created.permissions), false);
+        self.log_event(ctx, created.guild_id, LogCategory::ROLES, embed)
+            .await
+    }
+
+    pub async fn log_role_update(&self, ctx: &Context, updated: Role) -> LogResult<()> {
+        let previous = self
+            .roles
+            .lock()
+            .unwrap()
+            .insert(updated.id, updated.clone());
+        //nothing to compare against for roles that were never cached
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+
+        let changes = role_changes(&previous, &updated);
+        if changes.is_empty() || !self.is_logged(updated.guild_id, LogCategory::ROLES) {
+            return Ok(());
+        }
+
+        let mut embed = role_embed(ctx, &updated, Change::UPDATED).await;
+        for (name, value) in changes {
+            embed.field(name, value, false);
+        }
+        self.log_event(ctx, updated.guild_id, LogCategory::ROLES, embed)
+            .await
+    }
+
+    //roles are only sent as ids when they are deleted
+    pub async fn log_role_delete(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        role_id: RoleId,
+    ) -> LogResult<()> {
+        let deleted = self.roles.lock().unwrap().remove(&role_id);
+        if !self.is_logged(g_id, LogCategory::ROLES) {
+            return Ok(());
+        }
+
+        let mut embed = match &deleted {
+            Some(deleted) => {
+                let mut embed = role_embed(ctx, deleted, Change::DELETED).await;
+                embed.field("permissions:", permission_list(deleted.permissions), false);
+                embed
+            }
+            None => {
+                let mut embed = CreateEmbed::default();
+                embed
+                    .title(format!("role {}", Change::DELETED.name()))
+                    .field("role:", "unknown", true)
+                    .color(Change::DELETED.color());
+                embed
+            }
+        };
+        embed
+            .footer(|footer| footer.text(format!("role id: {role_id}")))
+            .timestamp(Timestamp::now());
+        self.log_event(ctx, g_id, LogCategory::ROLES, embed).await
+    }
+}
+
+//embed about a role with whoever changed it according to the audit log
+async fn role_embed(ctx: &Context, role: &Role, change: Change) -> CreateEmbed {
+    let mention = match change {
+        Change::DELETED => format!("@{}", role.name),
+        _ => format!("<@&{}> (@{})", role.id, role.name),
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("role {}", change.name()))
+        .field("role:", mention, true)
+        .footer(|footer| footer.text(format!("role id: {}", role.id)))
+        .timestamp(Timestamp::now())
+        .color(change.color());
+
+    let action = Action::Role(match change {
+        Change::CREATED => RoleAction::Create,
+        Change::UPDATED => RoleAction::Update,
+        Change::DELETED => RoleAction::Delete,
+    });
+    if let Some(entry) = audit_log::find_entry(ctx, role.guild_id, &[action], role.id.0).await {
+        embed.field("by:", entry.moderator_mention(), true);
+    }
+    embed
+}
+
+//the fields describing what changed between two versions of a role
+fn role_changes(before: &Role, after: &Role) -> Vec<(String, String)> {
+    let mut changes: Vec<(String, String)> = vec![];
+    if before.name != after.name {
+        changes.push((
+            "name:".to_owned(),
+            format!("@{} → @{}", before.name, after.name),
+        ));
+    }
+    let permissions = permission_diff(before.permissions, after.permissions);
+    if !permissions.is_empty() {
+        changes.push((
+            "permissions:".to_owned(),
+            excerpt(&permissions, FIELD_LIMIT - 3),
+        ));
+    }
+    if before.colour != after.colour {
+        changes.push((
+            "color:".to_owned(),
+            format!("#{} → #{}", before.colour.hex(), after.colour.hex()),
+        ));
+    }
+    if before.hoist != after.hoist {
+        changes.push((
+            "shown separately:".to_owned(),
+            on_off(before.hoist, after.hoist),
+        ));
+    }
+    if before.mentionable != after.mentionable {
+        changes.push((
+            "mentionable:".to_owned(),
+            on_off(before.mentionable, after.mentionable),
+        ));
+    }
+    if before.position != after.position {
+        changes.push((
+            "position:".to_owned(),
+            format!("{} → {}", before.position, after.position),
+        ));
+    }
+    changes
+}
+
+//every permission a role has, by name
+fn permission_list(permissions: Permissions) -> String {
+    if permissions.is_empty() {
+        return "none".to_owned();
+    }
+    excerpt(
+        &permissions.get_permission_names().join(", "),
+        FIELD_LIMIT - 3,
+    )
+}
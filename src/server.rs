@@ -0,0 +1,346 @@
+use crate::audit_log;
+use crate::config::LogCategory;
+use crate::error::LogResult;
+use crate::excerpt;
+use crate::Change;
+use crate::Handler;
+use crate::FIELD_LIMIT;
+use serenity::builder::CreateEmbed;
+use serenity::model::guild::audit_log::Action;
+use serenity::model::guild::audit_log::EmojiAction;
+use serenity::model::guild::audit_log::StickerAction;
+use serenity::model::guild::Emoji;
+use serenity::model::guild::Guild;
+use serenity::model::guild::PartialGuild;
+use serenity::model::guild::PremiumTier;
+use serenity::model::guild::VerificationLevel;
+use serenity::model::id::ChannelId;
+use serenity::model::id::EmojiId;
+use serenity::model::id::GuildId;
+use serenity::model::id::StickerId;
+use serenity::model::sticker::Sticker;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+//what is remembered about a guild's settings to diff its updates
+#[derive(Clone)]
+pub struct ServerSnapshot {
+    pub name: String,
+    pub icon_url: Option<String>,
+    pub verification_level: VerificationLevel,
+    pub vanity_url_code: Option<String>,
+    pub system_channel_id: Option<ChannelId>,
+    pub afk_channel_id: Option<ChannelId>,
+    pub afk_timeout: u64,
+    //decides how large the files uploaded to the log channel can be
+    pub premium_tier: PremiumTier,
+}
+
+impl From<&Guild> for ServerSnapshot {
+    fn from(guild: &Guild) -> Self {
+        ServerSnapshot {
+            name: guild.name.clone(),
+            icon_url: guild.icon_url(),
+            verification_level: guild.verification_level,
+            vanity_url_code: guild.vanity_url_code.clone(),
+            system_channel_id: guild.system_channel_id,
+            afk_channel_id: guild.afk_channel_id,
+            afk_timeout: guild.afk_timeout,
+            premium_tier: guild.premium_tier,
+        }
+    }
+}
+
+impl From<&PartialGuild> for ServerSnapshot {
+    fn from(guild: &PartialGuild) -> Self {
+        ServerSnapshot {
+            name: guild.name.clone(),
+            icon_url: guild.icon_url(),
+            verification_level: guild.verification_level,
+            vanity_url_code: guild.vanity_url_code.clone(),
+            system_channel_id: guild.system_channel_id,
+            afk_channel_id: guild.afk_channel_id,
+            afk_timeout: guild.afk_timeout,
+            premium_tier: guild.premium_tier,
+        }
+    }
+}
+
+impl Handler {
+    //remember a guild's settings, emojis and stickers once it becomes available
+    pub fn load_server(&self, guild: &Guild) {
+        self.servers
+            .lock()
+            .unwrap()
+            .insert(guild.id, ServerSnapshot::from(guild));
+        self.emojis
+            .lock()
+            .unwrap()
+            .insert(guild.id, emoji_names(&guild.emojis));
+        self.stickers
+            .lock()
+            .unwrap()
+            .insert(guild.id, sticker_names(&guild.stickers));
+    }
+
+    pub async fn log_server_update(&self, ctx: &Context, guild: PartialGuild) -> LogResult<()> {
+        let g_id = guild.id;
+        let updated = ServerSnapshot::from(&guild);
+        let previous = self.servers.lock().unwrap().insert(g_id, updated.clone());
+        //nothing to compare against for guilds that were never cached
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+
+        let changes = server_changes(&previous, &updated);
+        if changes.is_empty() || !self.is_logged(g_id, LogCategory::SERVER) {
+            return Ok(());
+        }
+
+        let mut embed = server_embed("server updated");
+        for (name, value) in changes {
+            embed.field(name, value, false);
+        }
+        if let Some(icon_url) = &updated.icon_url {
+            embed.thumbnail(icon_url);
+        }
+        let guild_update = [Action::GuildUpdate];
+        if let Some(entry) = audit_log::find_entry(ctx, g_id, &guild_update, g_id.0).await {
+            embed.field("by:", entry.moderator_mention(), true);
+        }
+        self.log_event(ctx, g_id, LogCategory::SERVER, embed).await
+    }
+
+    pub async fn log_emojis_update(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        current_state: HashMap<EmojiId, Emoji>,
+    ) -> LogResult<()> {
+        let updated = emoji_names(&current_state);
+        let previous = self.emojis.lock().unwrap().insert(g_id, updated.clone());
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+        if !self.is_logged(g_id, LogCategory::SERVER) {
+            return Ok(());
+        }
+
+        let (changes, changed) = named_changes(&previous, &updated);
+        let (change, first_id) = match changed {
+            Some(changed) => changed,
+            None => return Ok(()),
+        };
+
+        let mut embed = server_embed("emojis updated");
+        for (kind, names) in changes {
+            //show the emoji itself if it still exists
+            let shown: Vec<String> = names
+                .into_iter()
+                .map(|(id, name)| match current_state.get(&id) {
+                    Some(emoji) => format!("{emoji} {name}"),
+                    None => name,
+                })
+                .collect();
+            embed.field(
+                format!("{kind}:"),
+                excerpt(&shown.join("\n"), FIELD_LIMIT - 3),
+                false,
+            );
+        }
+        let action = Action::Emoji(match change {
+            Change::CREATED => EmojiAction::Create,
+            Change::UPDATED => EmojiAction::Update,
+            Change::DELETED => EmojiAction::Delete,
+        });
+        if let Some(entry) = audit_log::find_entry(ctx, g_id, &[action], first_id.0).await {
+            embed.field("by:", entry.moderator_mention(), true);
+        }
+        self.log_event(ctx, g_id, LogCategory::SERVER, embed).await
+    }
+
+    pub async fn log_stickers_update(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        current_state: HashMap<StickerId, Sticker>,
+    ) -> LogResult<()> {
+        let updated = sticker_names(&current_state);
+        let previous = self.stickers.lock().unwrap().insert(g_id, updated.clone());
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+        if !self.is_logged(g_id, LogCategory::SERVER) {
+            return Ok(());
+        }
+
+        let (changes, changed) = named_changes(&previous, &updated);
+        let (change, first_id) = match changed {
+            Some(changed) => changed,
+            None => return Ok(()),
+        };
+
+        let mut embed = server_embed("stickers updated");
+        for (kind, names) in changes {
+            let shown: Vec<String> = names.into_iter().map(|(_, name)| name).collect();
+            embed.field(
+                format!("{kind}:"),
+                excerpt(&shown.join("\n"), FIELD_LIMIT - 3),
+                false,
+            );
+        }
+        let action = Action::Sticker(match change {
+            Change::CREATED => StickerAction::Create,
+            Change::UPDATED => StickerAction::Update,
+            Change::DELETED => StickerAction::Delete,
+        });
+        if let Some(entry) = audit_log::find_entry(ctx, g_id, &[action], first_id.0).await {
+            embed.field("by:", entry.moderator_mention(), true);
+        }
+        self.log_event(ctx, g_id, LogCategory::SERVER, embed).await
+    }
+}
+
+fn server_embed(title: &str) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(title)
+        .timestamp(Timestamp::now())
+        .color(Change::UPDATED.color());
+    embed
+}
+
+//the fields describing what changed between two versions of a guild's settings
+fn server_changes(before: &ServerSnapshot, after: &ServerSnapshot) -> Vec<(String, String)> {
+    let channel = |c_id: Option<ChannelId>| match c_id {
+        Some(c_id) => format!("<#{c_id}>"),
+        None => "none".to_owned(),
+    };
+    let text = |text: &Option<String>| match text {
+        Some(text) => text.clone(),
+        None => "none".to_owned(),
+    };
+
+    let mut changes: Vec<(String, String)> = vec![];
+    if before.name != after.name {
+        changes.push((
+            "name:".to_owned(),
+            format!("{} → {}", before.name, after.name),
+        ));
+    }
+    if before.icon_url != after.icon_url {
+        let icon = |icon_url: &Option<String>| match icon_url {
+            Some(icon_url) => format!("[icon]({icon_url})"),
+            None => "none".to_owned(),
+        };
+        changes.push((
+            "icon:".to_owned(),
+            format!("{} → {}", icon(&before.icon_url), icon(&after.icon_url)),
+        ));
+    }
+    if before.verification_level != after.verification_level {
+        changes.push((
+            "verification level:".to_owned(),
+            format!(
+                "{:?} → {:?}",
+                before.verification_level, after.verification_level
+            )
+            .to_lowercase(),
+        ));
+    }
+    if before.vanity_url_code != after.vanity_url_code {
+        changes.push((
+            "vanity url:".to_owned(),
+            format!(
+                "{} → {}",
+                text(&before.vanity_url_code),
+                text(&after.vanity_url_code)
+            ),
+        ));
+    }
+    if before.system_channel_id != after.system_channel_id {
+        changes.push((
+            "system channel:".to_owned(),
+            format!(
+                "{} → {}",
+                channel(before.system_channel_id),
+                channel(after.system_channel_id)
+            ),
+        ));
+    }
+    if before.afk_channel_id != after.afk_channel_id {
+        changes.push((
+            "afk channel:".to_owned(),
+            format!(
+                "{} → {}",
+                channel(before.afk_channel_id),
+                channel(after.afk_channel_id)
+            ),
+        ));
+    }
+    if before.afk_timeout != after.afk_timeout {
+        changes.push((
+            "afk timeout:".to_owned(),
+            format!("{}s → {}s", before.afk_timeout, after.afk_timeout),
+        ));
+    }
+    changes
+}
+
+fn emoji_names(emojis: &HashMap<EmojiId, Emoji>) -> HashMap<EmojiId, String> {
+    emojis
+        .iter()
+        .map(|(id, emoji)| (*id, emoji.name.clone()))
+        .collect()
+}
+
+fn sticker_names(stickers: &HashMap<StickerId, Sticker>) -> HashMap<StickerId, String> {
+    stickers
+        .iter()
+        .map(|(id, sticker)| (*id, sticker.name.clone()))
+        .collect()
+}
+
+//ids and names of the items under each heading, like "added"
+type NamedChanges<K> = Vec<(&'static str, Vec<(K, String)>)>;
+
+//the added, removed and renamed items between two sets of named items, along
+//with one of the changes for looking it up in the audit log
+fn named_changes<K: Copy + Eq + Hash>(
+    before: &HashMap<K, String>,
+    after: &HashMap<K, String>,
+) -> (NamedChanges<K>, Option<(Change, K)>) {
+    let mut added: Vec<(K, String)> = vec![];
+    let mut renamed: Vec<(K, String)> = vec![];
+    for (id, name) in after {
+        match before.get(id) {
+            None => added.push((*id, format!(":{name}:"))),
+            Some(old_name) if old_name != name => {
+                renamed.push((*id, format!(":{old_name}: → :{name}:")))
+            }
+            Some(_) => {}
+        }
+    }
+    let removed: Vec<(K, String)> = before
+        .iter()
+        .filter(|(id, _)| !after.contains_key(id))
+        .map(|(id, name)| (*id, format!(":{name}:")))
+        .collect();
+
+    let changed = added
+        .first()
+        .map(|(id, _)| (Change::CREATED, *id))
+        .or_else(|| renamed.first().map(|(id, _)| (Change::UPDATED, *id)))
+        .or_else(|| removed.first().map(|(id, _)| (Change::DELETED, *id)));
+
+    let changes = [("added", added), ("renamed", renamed), ("removed", removed)]
+        .into_iter()
+        .filter(|(_, names)| !names.is_empty())
+        .collect();
+    (changes, changed)
+}
@@ -0,0 +1,188 @@
+use crate::channels::ChannelSnapshot;
+use crate::config::Toggle;
+use crate::excerpt;
+use crate::Handler;
+use crate::TITLE_LIMIT;
+use serde::Deserialize;
+use serenity::http::request::RequestBuilder;
+use serenity::http::routing::RouteInfo;
+use serenity::model::channel::Channel;
+use serenity::model::channel::ChannelType;
+use serenity::model::id::ChannelId;
+use serenity::model::prelude::GuildChannel;
+use serenity::prelude::*;
+use std::collections::HashMap;
+
+//room left in channel titles for " (continued)" and " (more files)"
+const TITLE_SUFFIX_ROOM: usize = 13;
+
+//forum fields of a channel that serenity doesn't parse yet
+#[derive(Deserialize)]
+struct ForumFields {
+    //tag ids on a forum post
+    #[serde(default)]
+    applied_tags: Vec<String>,
+    //tags that can be put on posts in a forum
+    #[serde(default)]
+    available_tags: Vec<ForumTag>,
+}
+
+#[derive(Deserialize)]
+struct ForumTag {
+    id: String,
+    name: String,
+}
+
+pub fn is_thread(kind: ChannelType) -> bool {
+    matches!(
+        kind,
+        ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread
+    )
+}
+
+impl Handler {
+    //whether messages in this channel are logged, threads and forum posts are
+    //only logged once a guild turns them on
+    pub fn is_logged_channel(&self, channel: &GuildChannel) -> bool {
+        !is_thread(channel.kind) || self.is_enabled(channel.guild_id, Toggle::THREADS)
+    }
+
+    //format a channel as `#channel_name`, or `#parent › thread_name` for
+    //threads, with the tags of forum posts
+    pub async fn channel_title(&self, ctx: &Context, channel: &GuildChannel) -> String {
+        let name = "#".to_owned() + channel.name();
+        if !is_thread(channel.kind) {
+            return name;
+        }
+        let parent_id = match channel.parent_id {
+            Some(parent_id) => parent_id,
+            None => return name,
+        };
+
+        let cached = self
+            .channels
+            .lock()
+            .unwrap()
+            .get(&parent_id)
+            .map(|parent| (parent.name.clone(), parent.kind));
+        let (parent_name, parent_kind) = match cached {
+            Some(parent) => parent,
+            None => match parent_id.to_channel(ctx).await {
+                Ok(Channel::Guild(parent)) => {
+                    self.channels
+                        .lock()
+                        .unwrap()
+                        .insert(parent_id, ChannelSnapshot::from(&parent));
+                    (parent.name, parent.kind)
+                }
+                _ => return name,
+            },
+        };
+        let title = format!("#{parent_name} › {}", channel.name());
+
+        //only posts in forums have tags
+        if parent_kind != ChannelType::Forum {
+            return title;
+        }
+        let tags = self.forum_tags(ctx, channel.id, parent_id).await;
+        tagged_title(title, &tags)
+    }
+
+    //names of the tags on a forum post, only the post is fetched once the
+    //forum's tags are cached
+    async fn forum_tags(
+        &self,
+        ctx: &Context,
+        post_id: ChannelId,
+        forum_id: ChannelId,
+    ) -> Vec<String> {
+        let applied = match forum_fields(ctx, post_id).await {
+            Some(post) => post.applied_tags,
+            None => return vec![],
+        };
+        if applied.is_empty() {
+            return vec![];
+        }
+
+        let cached = self
+            .channels
+            .lock()
+            .unwrap()
+            .get(&forum_id)
+            .and_then(|forum| forum.forum_tags.clone());
+        let forum_tags = match cached {
+            Some(forum_tags) => forum_tags,
+            None => {
+                let forum_tags: HashMap<String, String> = match forum_fields(ctx, forum_id).await {
+                    Some(forum) => forum
+                        .available_tags
+                        .into_iter()
+                        .map(|tag| (tag.id, tag.name))
+                        .collect(),
+                    None => return vec![],
+                };
+                if let Some(forum) = self.channels.lock().unwrap().get_mut(&forum_id) {
+                    forum.forum_tags = Some(forum_tags.clone());
+                }
+                forum_tags
+            }
+        };
+        applied
+            .iter()
+            .filter_map(|tag_id| forum_tags.get(tag_id).cloned())
+            .collect()
+    }
+}
+
+//add the tags of a forum post to its title, long forum and post names with
+//every tag don't fit in a title so the tags are cut off first
+fn tagged_title(title: String, tags: &[String]) -> String {
+    if tags.is_empty() {
+        return title;
+    }
+    excerpt(
+        &format!("{title} [{}]", tags.join(", ")),
+        TITLE_LIMIT - TITLE_SUFFIX_ROOM - 3,
+    )
+}
+
+//fetch a channel as raw json to read the fields serenity drops
+async fn forum_fields(ctx: &Context, c_id: ChannelId) -> Option<ForumFields> {
+    let request = RequestBuilder::new(RouteInfo::GetChannel { channel_id: c_id.0 }).build();
+    match ctx.http.fire::<ForumFields>(request).await {
+        Ok(fields) => Some(fields),
+        Err(why) => {
+            eprintln!("forum_fields(): unable to fetch channel {c_id}: {why}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tagged_title_lists_tags() {
+        let tags = vec!["bug".to_owned(), "help".to_owned()];
+        assert_eq!(
+            tagged_title("#forum › post".to_owned(), &tags),
+            "#forum › post [bug, help]"
+        );
+        assert_eq!(
+            tagged_title("#forum › post".to_owned(), &[]),
+            "#forum › post"
+        );
+    }
+
+    #[test]
+    fn tagged_title_leaves_room_for_suffixes() {
+        //the longest names and tags discord allows
+        let title = format!("#{} › {}", "f".repeat(100), "p".repeat(100));
+        let tags = vec!["t".repeat(20); 5];
+        let tagged = tagged_title(title.clone(), &tags);
+        assert!(tagged.starts_with(&title));
+        assert!(tagged.ends_with("..."));
+        assert_eq!(tagged.chars().count() + " (more files)".len(), TITLE_LIMIT);
+    }
+}
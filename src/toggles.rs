@@ -0,0 +1,104 @@
+use crate::command_guild;
+use crate::config::Toggle;
+use crate::error::LogError;
+use crate::error::LogResult;
+use crate::respond;
+use crate::Handler;
+use serenity::model::application::command::Command;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::id::GuildId;
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+
+const TOGGLE_LOG: &str = "togglelogging";
+
+const FEATURE_OPTION: &str = "feature";
+const ENABLED_OPTION: &str = "enabled";
+
+pub fn is_toggle_command(command_name: &str) -> bool {
+    command_name == TOGGLE_LOG
+}
+
+pub async fn register_commands(ctx: &Context) {
+    let _toggle_log = Command::create_global_application_command(ctx, |command| {
+        command.name(TOGGLE_LOG);
+        command.description("turn logging of noisy events on or off");
+        command.create_option(|option| {
+            option
+                .name(FEATURE_OPTION)
+                .description("the events to turn on or off")
+                .kind(CommandOptionType::String)
+                .required(true);
+            for toggle in Toggle::ALL {
+                option.add_string_choice(toggle.name(), toggle.name());
+            }
+            option
+        });
+        command.create_option(|option| {
+            option
+                .name(ENABLED_OPTION)
+                .description("whether these events are logged")
+                .kind(CommandOptionType::Boolean)
+                .required(true)
+        });
+        command.default_member_permissions(Permissions::MANAGE_GUILD)
+    })
+    .await;
+}
+
+impl Handler {
+    //whether a guild turned on logging these events
+    pub fn is_enabled(&self, g_id: GuildId, toggle: Toggle) -> bool {
+        self.map
+            .lock()
+            .unwrap()
+            .get(&g_id.to_string())
+            .is_some_and(|config| config.is_enabled(toggle))
+    }
+
+    //handle TOGGLE_LOG
+    pub async fn toggle_command(
+        &self,
+        ctx: &Context,
+        slash_command: &ApplicationCommandInteraction,
+    ) -> LogResult<()> {
+        let g_id = command_guild(slash_command)?;
+        let options = &slash_command.data.options;
+        let toggle = options
+            .iter()
+            .find(|option| option.name == FEATURE_OPTION)
+            .and_then(|option| option.value.as_ref())
+            .and_then(|value| value.as_str())
+            .and_then(Toggle::from_name)
+            .ok_or_else(|| {
+                LogError::InvalidCommand(format!("/{TOGGLE_LOG} needs the feature option"))
+            })?;
+        let enabled = options
+            .iter()
+            .find(|option| option.name == ENABLED_OPTION)
+            .and_then(|option| option.value.as_ref())
+            .and_then(|value| value.as_bool())
+            .ok_or_else(|| {
+                LogError::InvalidCommand(format!("/{TOGGLE_LOG} needs the enabled option"))
+            })?;
+
+        {
+            let mut map = self.map.lock().unwrap();
+            let toggles = &mut map.entry(g_id.to_string()).or_default().toggles;
+            if enabled {
+                toggles.insert(toggle);
+            } else {
+                toggles.remove(&toggle);
+            }
+        }
+        self.save_config(g_id)?;
+
+        let response = if enabled {
+            format!("{} will now be logged!", toggle.description())
+        } else {
+            format!("{} will no longer be logged!", toggle.description())
+        };
+        respond(ctx, slash_command, response).await
+    }
+}
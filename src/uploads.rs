@@ -0,0 +1,199 @@
+use crate::archive::ArchivedAttachment;
+use crate::Handler;
+use serenity::builder::CreateEmbed;
+use serenity::model::channel::Attachment;
+use serenity::model::channel::AttachmentType;
+use serenity::model::guild::PremiumTier;
+use serenity::model::id::GuildId;
+use serenity::prelude::*;
+use serenity::utils::Color;
+use std::borrow::Cow;
+
+//most files and embeds discord allows in one message
+pub const MAX_FILES: usize = 10;
+pub const MAX_EMBEDS: usize = 10;
+
+const MIB: u64 = 1024 * 1024;
+
+//a file ready to be uploaded to a log channel
+pub struct Upload<'a> {
+    pub filename: String,
+    pub bytes: Vec<u8>,
+    //what the file is described with if discord rejects the upload
+    pub attachment: &'a Attachment,
+    pub archived: Option<&'a ArchivedAttachment>,
+}
+
+impl From<Upload<'_>> for AttachmentType<'static> {
+    fn from(upload: Upload<'_>) -> Self {
+        AttachmentType::Bytes {
+            data: Cow::Owned(upload.bytes),
+            filename: upload.filename,
+        }
+    }
+}
+
+impl Handler {
+    //the largest upload a guild allows, which depends on its boost tier,
+    //guilds are only fetched if they weren't cached
+    pub async fn upload_limit(&self, ctx: &Context, g_id: GuildId) -> u64 {
+        let cached = self
+            .servers
+            .lock()
+            .unwrap()
+            .get(&g_id)
+            .map(|server| server.premium_tier);
+        let tier = match cached {
+            Some(tier) => tier,
+            None => match g_id.to_partial_guild(ctx).await {
+                Ok(guild) => guild.premium_tier,
+                Err(why) => {
+                    eprintln!("upload_limit(): unable to fetch {g_id}, assuming no boosts: {why}");
+                    PremiumTier::Tier0
+                }
+            },
+        };
+        tier_limit(tier)
+    }
+}
+
+fn tier_limit(tier: PremiumTier) -> u64 {
+    match tier {
+        PremiumTier::Tier2 => 50 * MIB,
+        PremiumTier::Tier3 => 100 * MIB,
+        _ => 10 * MIB,
+    }
+}
+
+//get the contents of an attachment, from the archive if it was archived
+pub async fn attachment_bytes(
+    attachment: &Attachment,
+    archived: Option<&ArchivedAttachment>,
+) -> Result<Vec<u8>, String> {
+    if let Some(archived) = archived {
+        match archived.read().await {
+            Ok(bytes) => return Ok(bytes),
+            Err(why) => eprintln!(
+                "attachment_bytes(): unable to read the archived {}: {why}",
+                archived.sha256
+            ),
+        }
+    }
+    attachment.download().await.map_err(|why| why.to_string())
+}
+
+//move uploads into the first message while they fit, returning the uploads
+//for the messages after it
+pub fn batch<'a>(
+    first: &mut Vec<Upload<'a>>,
+    uploads: Vec<Upload<'a>>,
+    limit: u64,
+) -> Vec<Vec<Upload<'a>>> {
+    let size =
+        |batch: &Vec<Upload>| -> u64 { batch.iter().map(|upload| upload.bytes.len() as u64).sum() };
+
+    let mut rest: Vec<Vec<Upload>> = vec![];
+    for upload in uploads {
+        let fits = |batch: &Vec<Upload>| {
+            batch.len() < MAX_FILES && size(batch) + upload.bytes.len() as u64 <= limit
+        };
+        if fits(first) {
+            first.push(upload);
+            continue;
+        }
+        match rest.last_mut() {
+            Some(batch) if fits(batch) => batch.push(upload),
+            _ => rest.push(vec![upload]),
+        }
+    }
+    rest
+}
+
+//describe an attachment that could not be uploaded, linking to discord's
+//copy for as long as it lasts
+pub fn skipped_embed(
+    attachment: &Attachment,
+    archived: Option<&ArchivedAttachment>,
+    reason: &str,
+) -> CreateEmbed {
+    let content_type = attachment.content_type.as_deref().unwrap_or("unknown");
+    let sha256 = match archived {
+        Some(archived) => format!("`{}`", archived.sha256),
+        None => "not archived".to_owned(),
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(&attachment.filename)
+        .url(&attachment.url)
+        .field("not uploaded:", reason, false)
+        .field("size:", file_size(attachment.size), true)
+        .field("type:", content_type, true)
+        .field("sha-256:", sha256, false)
+        .color(Color::ORANGE);
+    embed
+}
+
+pub fn file_size(bytes: u64) -> String {
+    if bytes >= MIB {
+        format!("{:.1} MiB", bytes as f64 / MIB as f64)
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::attachment;
+
+    fn upload(attachment: &Attachment, size: usize) -> Upload<'_> {
+        Upload {
+            filename: attachment.filename.clone(),
+            bytes: vec![0; size],
+            attachment,
+            archived: None,
+        }
+    }
+
+    fn sizes(batch: &[Upload]) -> Vec<usize> {
+        batch.iter().map(|upload| upload.bytes.len()).collect()
+    }
+
+    #[test]
+    fn batch_respects_file_count() {
+        let attachment = attachment("file.bin", None, 10);
+        let mut first: Vec<Upload> = vec![];
+        let uploads = (0..11).map(|_| upload(&attachment, 10)).collect();
+        let rest = batch(&mut first, uploads, 10 * MIB);
+        assert_eq!(first.len(), MAX_FILES);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].len(), 1);
+    }
+
+    #[test]
+    fn batch_respects_upload_limit() {
+        let attachment = attachment("file.bin", None, 100);
+        let mut first = vec![upload(&attachment, 40)];
+        let uploads = vec![
+            upload(&attachment, 50),
+            upload(&attachment, 60),
+            upload(&attachment, 30),
+            upload(&attachment, 100),
+        ];
+        let rest = batch(&mut first, uploads, 100);
+        assert_eq!(sizes(&first), vec![40, 50]);
+        assert_eq!(rest.len(), 2);
+        assert_eq!(sizes(&rest[0]), vec![60, 30]);
+        assert_eq!(sizes(&rest[1]), vec![100]);
+    }
+
+    #[test]
+    fn file_sizes() {
+        assert_eq!(file_size(512), "512 B");
+        assert_eq!(file_size(1536), "1.5 KiB");
+        assert_eq!(file_size(10 * MIB), "10.0 MiB");
+    }
+}
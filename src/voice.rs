@@ -0,0 +1,183 @@
+use crate::audit_log;
+use crate::config::LogCategory;
+use crate::config::Toggle;
+use crate::error::LogResult;
+use crate::member_face;
+use crate::member_name;
+use crate::Handler;
+use serenity::builder::CreateEmbed;
+use serenity::model::guild::audit_log::Action;
+use serenity::model::guild::audit_log::MemberAction;
+use serenity::model::guild::Guild;
+use serenity::model::id::ChannelId;
+use serenity::model::id::GuildId;
+use serenity::model::voice::VoiceState;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use serenity::utils::Color;
+
+//where a member is in voice and since when
+#[derive(Clone, Copy)]
+pub struct VoiceSession {
+    pub channel_id: ChannelId,
+    //unknown for members that were already in voice when the bot started
+    pub joined_at: Option<Timestamp>,
+    //server mute and deafen, set by moderators
+    pub mute: bool,
+    pub deaf: bool,
+}
+
+impl Handler {
+    //remember who is in voice once a guild becomes available
+    pub fn load_voice_states(&self, guild: &Guild) {
+        let mut sessions = self.voice.lock().unwrap();
+        for (user_id, state) in &guild.voice_states {
+            if let Some(channel_id) = state.channel_id {
+                sessions.insert(
+                    (guild.id, *user_id),
+                    VoiceSession {
+                        channel_id,
+                        joined_at: None,
+                        mute: state.mute,
+                        deaf: state.deaf,
+                    },
+                );
+            }
+        }
+    }
+
+    pub async fn log_voice_state(&self, ctx: &Context, state: VoiceState) -> LogResult<()> {
+        let g_id = match state.guild_id {
+            Some(g_id) => g_id,
+            None => return Ok(()),
+        };
+
+        //sessions are tracked even when voice isn't logged so that durations
+        //are right once it is turned on
+        let key = (g_id, state.user_id);
+        let previous = self.voice.lock().unwrap().get(&key).copied();
+        let current = state.channel_id.map(|channel_id| VoiceSession {
+            channel_id,
+            joined_at: match previous {
+                Some(previous) => previous.joined_at,
+                None => Some(Timestamp::now()),
+            },
+            mute: state.mute,
+            deaf: state.deaf,
+        });
+        {
+            let mut sessions = self.voice.lock().unwrap();
+            match current {
+                Some(current) => sessions.insert(key, current),
+                None => sessions.remove(&key),
+            };
+        }
+
+        //voice is noisy, it is only logged when the guild turned it on
+        if !self.is_enabled(g_id, Toggle::VOICE) || !self.is_logged(g_id, LogCategory::VOICE) {
+            return Ok(());
+        }
+
+        let mut embeds: Vec<CreateEmbed> = vec![];
+        match (previous, current) {
+            (None, Some(current)) => {
+                let mut embed = voice_embed(&state, "joined voice:", Color::DARK_GREEN);
+                embed.field("channel:", format!("<#{}>", current.channel_id), true);
+                embeds.push(embed);
+            }
+            (Some(previous), None) => {
+                let mut embed = voice_embed(&state, "left voice:", Color::DARK_RED);
+                embed.field("channel:", format!("<#{}>", previous.channel_id), true);
+                if let Some(joined_at) = previous.joined_at {
+                    let seconds = Timestamp::now().unix_timestamp() - joined_at.unix_timestamp();
+                    embed.field("in voice for:", format_duration(seconds), true);
+                }
+                embeds.push(embed);
+            }
+            (Some(previous), Some(current)) => {
+                if previous.channel_id != current.channel_id {
+                    let mut embed = voice_embed(&state, "moved voice channel:", Color::BLUE);
+                    embed
+                        .field("from:", format!("<#{}>", previous.channel_id), true)
+                        .field("to:", format!("<#{}>", current.channel_id), true);
+                    embeds.push(embed);
+                }
+                if previous.mute != current.mute || previous.deaf != current.deaf {
+                    let embed = self
+                        .server_mute_embed(ctx, g_id, &state, previous, current)
+                        .await;
+                    embeds.push(embed);
+                }
+            }
+            (None, None) => {}
+        }
+
+        for embed in embeds {
+            self.log_event(ctx, g_id, LogCategory::VOICE, embed).await?;
+        }
+        Ok(())
+    }
+
+    //a moderator server muted or deafened a member, or undid it
+    async fn server_mute_embed(
+        &self,
+        ctx: &Context,
+        g_id: GuildId,
+        state: &VoiceState,
+        previous: VoiceSession,
+        current: VoiceSession,
+    ) -> CreateEmbed {
+        let mut changes: Vec<&str> = vec![];
+        if previous.mute != current.mute {
+            changes.push(if current.mute {
+                "server muted"
+            } else {
+                "server unmuted"
+            });
+        }
+        if previous.deaf != current.deaf {
+            changes.push(if current.deaf {
+                "server deafened"
+            } else {
+                "server undeafened"
+            });
+        }
+
+        let mut embed = voice_embed(state, &format!("{}:", changes.join(" and ")), Color::GOLD);
+        embed.field("channel:", format!("<#{}>", current.channel_id), true);
+        let update = [Action::Member(MemberAction::Update)];
+        if let Some(entry) = audit_log::find_entry(ctx, g_id, &update, state.user_id.0).await {
+            embed.field("by:", entry.moderator_mention(), true);
+        }
+        embed
+    }
+}
+
+//embed about a member's voice activity
+fn voice_embed(state: &VoiceState, title: &str, color: Color) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    if let Some(member) = &state.member {
+        let name = member_name(&member.user, member.nick.as_ref());
+        let face = member_face(member.guild_id, &member.user, member.avatar.as_ref());
+        embed.author(|author| author.name(name).icon_url(face));
+    }
+    embed
+        .field(title, format!("<@{}>", state.user_id), true)
+        .footer(|footer| footer.text(format!("user id: {}", state.user_id)))
+        .timestamp(Timestamp::now())
+        .color(color);
+    embed
+}
+
+//show a number of seconds as hours, minutes and seconds
+pub fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}